@@ -0,0 +1,244 @@
+use crate::color_reflection_window::ReflectionMode;
+use crate::utils::GrayscaleMode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// PNG中 "anchors" 文本块保存的反射配置
+///
+/// 版本历史：
+/// - v0：手写JSON，没有 version 字段，锚点取整保存
+/// - v1：serde序列化，锚点保留小数，附带输出调色板与写入程序的版本
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorsMetadata {
+    pub version: u32,
+    pub app_version: String,
+    pub anchors: Vec<f32>,
+    pub reflection_mode: ReflectionMode,
+    pub grayscale_mode: GrayscaleMode,
+    // 应用反射后输出的灰度级
+    #[serde(default)]
+    pub palette: Vec<u8>,
+    #[serde(default)]
+    pub regions: Vec<RegionMetadata>,
+}
+
+/// 分区配置（蒙版以行程文本保存，见 ImageProcessor::encode_mask_rle）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionMetadata {
+    #[serde(default)]
+    pub name: String,
+    pub anchors: Vec<f32>,
+    pub reflection_mode: ReflectionMode,
+    pub grayscale_mode: GrayscaleMode,
+    pub mask: String,
+}
+
+impl AnchorsMetadata {
+    pub const CURRENT_VERSION: u32 = 1;
+
+    pub fn new(
+        anchors: Vec<f32>,
+        reflection_mode: ReflectionMode,
+        grayscale_mode: GrayscaleMode,
+        palette: Vec<u8>,
+        regions: Vec<RegionMetadata>,
+    ) -> Self {
+        Self {
+            version: Self::CURRENT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            anchors,
+            reflection_mode,
+            grayscale_mode,
+            palette,
+            regions,
+        }
+    }
+
+    /// 序列化为JSON（UTF-8，写入时按需使用iTXt）
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// 解析任意已知版本的元数据，旧版本迁移为当前结构
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        let Value::Object(object) = &value else {
+            return Err("metadata is not a JSON object".to_string());
+        };
+        let metadata = match object.get("version") {
+            None => Self::migrate_v0(&value)?,
+            Some(version) => match version.as_u64() {
+                Some(v) if v == Self::CURRENT_VERSION as u64 => {
+                    serde_json::from_value(value).map_err(|e| format!("invalid metadata: {}", e))?
+                }
+                Some(v) if v > Self::CURRENT_VERSION as u64 => {
+                    return Err(format!(
+                        "metadata version {} is newer than this app supports ({})",
+                        v,
+                        Self::CURRENT_VERSION
+                    ));
+                }
+                _ => return Err(format!("unknown metadata version {}", version)),
+            },
+        };
+        metadata.validate()?;
+        Ok(metadata)
+    }
+
+    /// v0：{"anchors":[..],"reflectionMode":"..","grayscaleMode":"..","regions":[..]}
+    ///
+    /// 旧读取器只要求 anchors，模式缺失时取默认值；损坏的区域逐个跳过
+    fn migrate_v0(value: &Value) -> Result<Self, String> {
+        let anchors = Self::anchors_field(value).ok_or("missing or invalid \"anchors\" array")?;
+        let reflection_mode = match value.get("reflectionMode") {
+            Some(mode) => serde_json::from_value(mode.clone()).map_err(|e| format!("invalid reflectionMode: {}", e))?,
+            None => ReflectionMode::Average,
+        };
+        let grayscale_mode = match value.get("grayscaleMode") {
+            Some(mode) => serde_json::from_value(mode.clone()).map_err(|e| format!("invalid grayscaleMode: {}", e))?,
+            None => GrayscaleMode::Default,
+        };
+        let regions = value
+            .get("regions")
+            .and_then(Value::as_array)
+            .map(|regions| {
+                regions
+                    .iter()
+                    .filter_map(|r| serde_json::from_value::<RegionMetadata>(r.clone()).ok())
+                    .filter(|r| Self::validate_anchors(&r.anchors).is_ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            version: 0,
+            app_version: String::new(),
+            anchors,
+            reflection_mode,
+            grayscale_mode,
+            palette: Vec::new(),
+            regions,
+        })
+    }
+
+    fn anchors_field(value: &Value) -> Option<Vec<f32>> {
+        value
+            .get("anchors")?
+            .as_array()?
+            .iter()
+            .map(|v| v.as_f64().map(|v| v as f32))
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        Self::validate_anchors(&self.anchors)?;
+        for region in &self.regions {
+            Self::validate_anchors(&region.anchors).map_err(|e| format!("region '{}': {}", region.name, e))?;
+        }
+        Ok(())
+    }
+
+    fn validate_anchors(anchors: &[f32]) -> Result<(), String> {
+        if anchors.is_empty() {
+            return Err("no anchors".to_string());
+        }
+        match anchors.iter().find(|v| !v.is_finite() || **v < 0.0 || **v > 255.0) {
+            Some(v) => Err(format!("anchor {} is outside 0-255", v)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> AnchorsMetadata {
+        AnchorsMetadata::new(
+            vec![12.5, 128.25, 240.0],
+            ReflectionMode::Partial,
+            GrayscaleMode::Max,
+            vec![0, 127, 255],
+            vec![RegionMetadata {
+                name: "花纹 \"A\"".to_string(),
+                anchors: vec![64.75],
+                reflection_mode: ReflectionMode::Average,
+                grayscale_mode: GrayscaleMode::Min,
+                mask: "2x1;1,1".to_string(),
+            }],
+        )
+    }
+
+    #[test]
+    fn round_trip_keeps_fractional_anchors_and_modes() {
+        let metadata = sample();
+        let json = metadata.to_json();
+        assert_eq!(AnchorsMetadata::parse(&json), Ok(metadata));
+    }
+
+    #[test]
+    fn migrates_v0() {
+        let json = r#"{"anchors":[10,128,200],"reflectionMode":"Partial","grayscaleMode":"Min","regions":[{"name":"a\"bé","anchors":[50],"reflectionMode":"Average","grayscaleMode":"Max","mask":"1x1;0,1"}]}"#;
+        let metadata = AnchorsMetadata::parse(json).unwrap();
+        assert_eq!(metadata.version, 0);
+        assert_eq!(metadata.anchors, vec![10.0, 128.0, 200.0]);
+        assert_eq!(metadata.reflection_mode, ReflectionMode::Partial);
+        assert_eq!(metadata.grayscale_mode, GrayscaleMode::Min);
+        assert_eq!(metadata.regions.len(), 1);
+        assert_eq!(metadata.regions[0].name, "a\"bé");
+        assert_eq!(metadata.regions[0].grayscale_mode, GrayscaleMode::Max);
+    }
+
+    #[test]
+    fn migrates_v0_with_only_anchors() {
+        let metadata = AnchorsMetadata::parse(r#"{"anchors":[100]}"#).unwrap();
+        assert_eq!(metadata.anchors, vec![100.0]);
+        assert_eq!(metadata.reflection_mode, ReflectionMode::Average);
+        assert_eq!(metadata.grayscale_mode, GrayscaleMode::Default);
+        assert!(metadata.regions.is_empty());
+    }
+
+    #[test]
+    fn v0_skips_broken_regions() {
+        let json = r#"{"anchors":[1],"regions":[{"name":"bad"},{"anchors":[2],"reflectionMode":"Average","grayscaleMode":"Default","mask":"1x1;1"}]}"#;
+        let metadata = AnchorsMetadata::parse(json).unwrap();
+        assert_eq!(metadata.regions.len(), 1);
+        assert_eq!(metadata.regions[0].anchors, vec![2.0]);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let cases = [
+            "",
+            "not json",
+            r#"{"anchors":[1,2"#,
+            "[1,2,3]",
+            "null",
+            r#"{}"#,
+            r#"{"anchors":[]}"#,
+            r#"{"anchors":"12,34"}"#,
+            r#"{"anchors":[1,"x"]}"#,
+            r#"{"anchors":[-1]}"#,
+            r#"{"anchors":[256]}"#,
+            r#"{"anchors":[1e40]}"#,
+            r#"{"anchors":[1],"reflectionMode":"Sideways"}"#,
+            r#"{"anchors":[1],"grayscaleMode":3}"#,
+            r#"{"version":"1","anchors":[1]}"#,
+            r#"{"version":-1,"anchors":[1]}"#,
+            r#"{"version":1,"anchors":[1]}"#,
+            r#"{"version":1,"appVersion":"0.1.0","anchors":[1],"reflectionMode":"Average","grayscaleMode":"Default","regions":[{"anchors":[],"reflectionMode":"Average","grayscaleMode":"Default","mask":""}]}"#,
+        ];
+        for case in cases {
+            assert!(AnchorsMetadata::parse(case).is_err(), "accepted {:?}", case);
+        }
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let mut value = serde_json::to_value(sample()).unwrap();
+        value["version"] = Value::from(AnchorsMetadata::CURRENT_VERSION + 1);
+        let err = AnchorsMetadata::parse(&value.to_string()).unwrap_err();
+        assert!(err.contains("newer"));
+    }
+}
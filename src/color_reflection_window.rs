@@ -1,6 +1,8 @@
-use egui;
-use image::DynamicImage;
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use crate::anchors_metadata::{AnchorsMetadata, RegionMetadata};
+use crate::notifications;
 use crate::utils::{GrayscaleMode, ImageProcessor};
 
 /// Color Reflection窗口的状态
@@ -14,15 +16,34 @@ pub struct ColorReflectionWindow {
     pub last_applied_slider_values: Option<Vec<f32>>,
     pub last_reflection_mode: Option<ReflectionMode>,
     pub has_applied_reflection: bool,
-    pub message: Option<String>,
+    // 分区配置：每个区域有自己的锚点与模式，叠加在全图配置之上
+    pub regions: Vec<Region>,
+    pub last_applied_regions: Vec<Region>,
+    // 区段隔离：悬停或点选滑动条上的区段时只突出显示该区段的像素
+    pub isolate_segments: bool,
+    pub hovered_segment: Option<usize>,
+    pub selected_segment: Option<usize>,
+    // 拖动锚点时在图像上描出阈值边界
+    pub show_contours: bool,
+    pub dragging_anchor: Option<usize>,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ReflectionMode {
     Average,
     Partial,
 }
 
+/// 命名区域及其独立的反射配置
+#[derive(Clone)]
+pub struct Region {
+    pub name: String,
+    pub slider_values: Vec<f32>,
+    pub reflection_mode: ReflectionMode,
+    pub grayscale_mode: GrayscaleMode,
+    pub mask: GrayImage,
+}
+
 impl Default for ColorReflectionWindow {
     fn default() -> Self {
         Self {
@@ -34,14 +55,21 @@ impl Default for ColorReflectionWindow {
             last_applied_slider_values: None,
             last_reflection_mode: None,
             has_applied_reflection: false,
-            message: None,
+            regions: Vec::new(),
+            last_applied_regions: Vec::new(),
+            isolate_segments: false,
+            hovered_segment: None,
+            selected_segment: None,
+            show_contours: false,
+            dragging_anchor: None,
         }
     }
 }
 
 impl ColorReflectionWindow {
     /// 显示Color Reflection窗口
-    pub fn show(&mut self, ctx: &egui::Context, original_image: &Option<DynamicImage>, current_texture: &mut Option<egui::TextureHandle>, temp_path: &Option<PathBuf>, current_path: &Option<PathBuf>, grayscale_mode: &mut GrayscaleMode) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn show(&mut self, ctx: &egui::Context, original_image: &Option<DynamicImage>, current_texture: &mut Option<egui::TextureHandle>, temp_path: &Option<PathBuf>, revision: &mut u64, current_path: &Option<PathBuf>, grayscale_mode: &mut GrayscaleMode, mask: Option<&GrayImage>) {
         if self.show_window {
             let mut show_window = self.show_window;
             egui::Window::new("Color Reflection")
@@ -49,28 +77,14 @@ impl ColorReflectionWindow {
                 .default_size([800.0, 600.0])
                 .resizable(true)
                 .show(ctx, |ui| {
-                    self.show_content(ui, ctx, original_image, current_texture, temp_path, current_path, grayscale_mode);
+                    self.show_content(ui, ctx, original_image, current_texture, temp_path, revision, current_path, grayscale_mode, mask);
                 });
             self.show_window = show_window;
-
-            // 简单提示窗口（克隆消息，避免借用冲突）
-            if let Some(msg_owned) = self.message.clone() {
-                let mut open = true;
-                let mut clear_message = false;
-                egui::Window::new("Info")
-                    .open(&mut open)
-                    .collapsible(false)
-                    .resizable(false)
-                    .show(ctx, |ui| {
-                        ui.label(msg_owned);
-                        if ui.button("OK").clicked() { clear_message = true; }
-                    });
-                if clear_message || !open { self.message = None; }
-            }
         }
     }
 
     /// 显示窗口内容
+    #[allow(clippy::too_many_arguments)]
     fn show_content(
         &mut self,
         ui: &mut egui::Ui,
@@ -78,8 +92,10 @@ impl ColorReflectionWindow {
         original_image: &Option<DynamicImage>,
         current_texture: &mut Option<egui::TextureHandle>,
         temp_path: &Option<PathBuf>,
+        revision: &mut u64,
         current_path: &Option<PathBuf>,
         grayscale_mode: &mut GrayscaleMode,
+        mask: Option<&GrayImage>,
     ) {
         ui.heading("Color Reflection");
         ui.separator();
@@ -109,7 +125,7 @@ impl ColorReflectionWindow {
             // 确认按钮
             if ui.button("Confirm Slider Amount").clicked() {
                 if let Ok(amount) = self.slider_amount_input.parse::<usize>() {
-                    if amount >= 1 && amount <= 10 {
+                    if (1..=10).contains(&amount) {
                         self.slider_amount = Some(amount);
                         self.update_slider_values();
                     }
@@ -117,32 +133,7 @@ impl ColorReflectionWindow {
             }
 
             if ui.button("Load Anchors From PNG").clicked() {
-                // 优先读取当前文件，如无则回退到临时文件
-                let pick_path = current_path.as_ref().or(temp_path.as_ref());
-                if let Some(p) = pick_path {
-                    match crate::utils::ImageProcessor::read_png_text_value_from_path(p.as_path(), "anchors") {
-                        Ok(Some(json)) => {
-                            if let Some(vals) = Self::parse_anchors_from_json(&json) {
-                                self.slider_amount = Some(vals.len());
-                                self.slider_amount_input = vals.len().to_string();
-                                self.slider_values = vals;
-                                if let Some(g) = Self::parse_grayscale_from_json(&json) {
-                                    *grayscale_mode = g;
-                                }
-                            } else {
-                                self.message = Some("No slider anchors found in metadata".to_string());
-                            }
-                        }
-                        Ok(None) => {
-                            self.message = Some("No slider anchors found in metadata".to_string());
-                        }
-                        Err(e) => {
-                            self.message = Some(format!("Failed to read anchors: {}", e));
-                        }
-                    }
-                } else {
-                    self.message = Some("No temp image available".to_string());
-                }
+                self.load_anchors_from_png(current_path, temp_path, grayscale_mode);
             }
         });
 
@@ -165,6 +156,16 @@ impl ColorReflectionWindow {
                     self.draw_slider_track(ui);
                 }
             );
+
+            ui.horizontal(|ui| {
+                if ui
+                    .checkbox(&mut self.isolate_segments, "Isolate segment (hover or click the track)")
+                    .changed()
+                {
+                    self.selected_segment = None;
+                }
+                ui.checkbox(&mut self.show_contours, "Threshold contours while dragging");
+            });
         } else {
             ui.label("Please enter slider amount (1-10) and click confirm");
         }
@@ -178,11 +179,15 @@ impl ColorReflectionWindow {
             ui.radio_value(&mut self.reflection_mode, ReflectionMode::Partial, "Partial");
         });
 
+        ui.add_space(10.0);
+
+        self.show_regions(ui, grayscale_mode, mask);
+
         ui.add_space(20.0);
 
         // Confirm Reflection 按钮
         if ui.button("Confirm Reflection").clicked() {
-            self.apply_color_reflection(ctx, original_image, current_texture, temp_path, grayscale_mode);
+            self.apply_color_reflection(ctx, original_image, current_texture, temp_path, revision, grayscale_mode, mask);
         }
 
 
@@ -214,6 +219,23 @@ impl ColorReflectionWindow {
         // 滑动条轨道边框
         painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::from_gray(120)), egui::StrokeKind::Outside);
 
+        // 区段隔离：记录悬停的区段，并在轨道上框出突出的区段
+        self.hovered_segment = None;
+        if self.isolate_segments {
+            if let Some(mouse_pos) = ui.input(|i| i.pointer.hover_pos()).filter(|p| rect.contains(*p)) {
+                let value = ((mouse_pos.x - rect.min.x) / rect.width()).clamp(0.0, 1.0) * 255.0;
+                self.hovered_segment = Some(ImageProcessor::segment_index(value as u8, &self.slider_values));
+            }
+            if let Some(segment) = self.hovered_segment.or(self.selected_segment) {
+                let (start, end) = self.segment_bounds(segment);
+                let segment_rect = egui::Rect::from_min_max(
+                    egui::pos2(rect.min.x + start / 255.0 * rect.width(), rect.min.y),
+                    egui::pos2(rect.min.x + end / 255.0 * rect.width(), rect.max.y),
+                );
+                painter.rect_stroke(segment_rect, 0.0, egui::Stroke::new(2.0, egui::Color32::YELLOW), egui::StrokeKind::Inside);
+            }
+        }
+
         // 绘制滑块
         for (i, &value) in self.slider_values.iter().enumerate() {
             // 将0-255的值映射到滑动条位置
@@ -262,11 +284,21 @@ impl ColorReflectionWindow {
                 // 找到最近的滑块并移动它
                 if let Some(closest_idx) = self.find_closest_slider(value) {
                     self.slider_values[closest_idx] = value;
+                    self.dragging_anchor = Some(closest_idx);
                 }
             }
+        } else {
+            self.dragging_anchor = None;
         }
 
-        if response.clicked() {
+        // 隔离模式下点击用于选中区段（再次点击取消）
+        if response.clicked() && self.isolate_segments {
+            self.selected_segment = if self.selected_segment == self.hovered_segment {
+                None
+            } else {
+                self.hovered_segment
+            };
+        } else if response.clicked() {
             if let Some(mouse_pos) = ui.input(|i| i.pointer.hover_pos()) {
                 let relative_x = (mouse_pos.x - rect.min.x) / rect.width();
                 let relative_x = relative_x.clamp(0.0, 1.0);
@@ -284,6 +316,7 @@ impl ColorReflectionWindow {
 
     /// 更新滑块值
     fn update_slider_values(&mut self) {
+        self.selected_segment = None;
         if let Some(amount) = self.slider_amount {
             self.slider_values.clear();
             for i in 0..amount {
@@ -297,6 +330,31 @@ impl ColorReflectionWindow {
         }
     }
 
+    /// 区段在0-255上的起止位置（第0段从0开始，最后一段到255结束）
+    fn segment_bounds(&self, segment: usize) -> (f32, f32) {
+        let mut sorted_values = self.slider_values.clone();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let start = if segment == 0 { 0.0 } else { sorted_values.get(segment - 1).copied().unwrap_or(255.0) };
+        let end = sorted_values.get(segment).copied().unwrap_or(255.0);
+        (start, end)
+    }
+
+    /// 主显示区应突出显示的区段（窗口关闭时不生效）
+    pub fn highlighted_segment(&self) -> Option<usize> {
+        if !self.show_window || !self.isolate_segments {
+            return None;
+        }
+        self.hovered_segment.or(self.selected_segment)
+    }
+
+    /// 正在拖动的锚点阈值（用于绘制等高线）
+    pub fn contour_threshold(&self) -> Option<f32> {
+        if !self.show_window || !self.show_contours {
+            return None;
+        }
+        self.dragging_anchor.and_then(|i| self.slider_values.get(i).copied())
+    }
+
     /// 找到最近的滑块
     fn find_closest_slider(&self, target_pos: f32) -> Option<usize> {
         if self.slider_values.is_empty() {
@@ -317,47 +375,91 @@ impl ColorReflectionWindow {
         Some(closest_idx)
     }
 
+    /// 从当前PNG（无则临时文件）的元数据读取锚点、灰度模式与分区
+    pub fn load_anchors_from_png(
+        &mut self,
+        current_path: &Option<PathBuf>,
+        temp_path: &Option<PathBuf>,
+        grayscale_mode: &mut GrayscaleMode,
+    ) {
+        // 优先读取当前文件（仅PNG可含锚点），否则回退到临时文件
+        let pick_path = current_path
+            .as_ref()
+            .filter(|p| ImageProcessor::is_png(p))
+            .or(temp_path.as_ref());
+        if let Some(p) = pick_path {
+            match crate::utils::ImageProcessor::read_png_text_value_from_path(p.as_path(), "anchors") {
+                Ok(Some(json)) => {
+                    match AnchorsMetadata::parse(&json) {
+                        Ok(metadata) => {
+                            // 蒙版须与当前图像同尺寸
+                            let size = temp_path.as_ref().and_then(|t| image::image_dimensions(t).ok());
+                            self.apply_metadata(metadata, size, grayscale_mode)
+                        }
+                        Err(e) => notifications::warn(format!("Could not read anchors metadata: {}", e)),
+                    }
+                }
+                Ok(None) => {
+                    notifications::warn("No slider anchors found in metadata");
+                }
+                Err(e) => {
+                    notifications::error(format!("Failed to read anchors: {}", e));
+                }
+            }
+        } else {
+            notifications::warn("No temp image available");
+        }
+    }
+
     /// 应用颜色反射处理
-    fn apply_color_reflection(
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_color_reflection(
         &mut self,
         ctx: &egui::Context,
         original_image: &Option<DynamicImage>,
         current_texture: &mut Option<egui::TextureHandle>,
         temp_path: &Option<PathBuf>,
+        revision: &mut u64,
         grayscale_mode: &GrayscaleMode,
+        mask: Option<&GrayImage>,
     ) {
         if let Some(original_img) = original_image {
             if self.slider_values.is_empty() {
-                eprintln!("No sliders configured for color reflection");
+                notifications::warn("No sliders configured for color reflection");
                 return;
             }
 
             // 根据选择的模式应用颜色反射处理
-            let processed_img = match self.reflection_mode {
-                ReflectionMode::Average => {
-                    ImageProcessor::apply_color_reflection_with_mode(original_img, &self.slider_values, *grayscale_mode)
-                }
-                ReflectionMode::Partial => {
-                    ImageProcessor::apply_color_reflection_partial_with_mode(original_img, &self.slider_values, *grayscale_mode)
+            let mut processed_img = Self::render(original_img, &self.slider_values, self.reflection_mode, *grayscale_mode);
+
+            // 各区域按顺序用自己的配置覆盖区域内的像素
+            for region in &self.regions {
+                if region.mask.dimensions() != (original_img.width(), original_img.height()) {
+                    notifications::warn(format!("Skipping region '{}': mask size does not match the image", region.name));
+                    continue;
                 }
-            };
+                let region_img = Self::render(original_img, &region.slider_values, region.reflection_mode, region.grayscale_mode);
+                processed_img = ImageProcessor::composite_masked(&processed_img, &region_img, &region.mask);
+            }
+            // 有选区时只替换选区内的像素
+            let processed_img = ImageProcessor::limit_to_mask(processed_img, mask, temp_path);
 
             // 更新显示
             *current_texture = Some(ImageProcessor::update_texture_from_image(&processed_img, ctx));
 
             // 保存到临时文件
             if let Some(temp_path) = temp_path {
-                if let Err(e) = ImageProcessor::save_to_temp(&processed_img, temp_path) {
-                    eprintln!("Failed to save to temp file: {}", e);
+                if let Err(e) = ImageProcessor::save_to_temp(&processed_img, temp_path, revision) {
+                    notifications::error(format!("Failed to save to temp file: {}", e));
                 }
             }
 
             // 记录快照以便后续保存元数据
             self.snapshot_after_apply();
 
-            println!("Color reflection applied successfully");
+            notifications::info("Color reflection applied successfully");
         } else {
-            eprintln!("No image loaded for color reflection");
+            notifications::warn("No image loaded for color reflection");
         }
     }
 }
@@ -366,74 +468,210 @@ impl ColorReflectionWindow {
     /// 在反射应用成功后记录快照（供保存元数据使用）
     pub fn snapshot_after_apply(&mut self) {
         self.last_applied_slider_values = Some(self.slider_values.clone());
-        self.last_reflection_mode = Some(self.reflection_mode.clone());
+        self.last_reflection_mode = Some(self.reflection_mode);
+        self.last_applied_regions = self.regions.clone();
         self.has_applied_reflection = true;
     }
 
-    /// 构造包含锚点与模式信息的JSON字符串
-    pub fn build_anchors_metadata_json(&self, grayscale_mode: &GrayscaleMode) -> Option<String> {
+    /// 最近一次应用反射后输出的灰度级（按区段顺序）
+    pub fn applied_levels(&self) -> Option<Vec<u8>> {
         if !self.has_applied_reflection {
             return None;
         }
-        let anchors = self
-            .last_applied_slider_values
-            .as_ref()
-            .map(|v| {
-                let values = v.iter().map(|x| format!("{:.0}", x)).collect::<Vec<_>>();
-                format!("[{}]", values.join(","))
-            })?;
+        let values = self.last_applied_slider_values.as_ref()?;
+        let mut levels = Self::levels_for(values, self.last_reflection_mode?);
+        // 分区输出值并入调色板
+        for region in &self.last_applied_regions {
+            for v in Self::levels_for(&region.slider_values, region.reflection_mode) {
+                if !levels.contains(&v) {
+                    levels.push(v);
+                }
+            }
+        }
+        if !self.last_applied_regions.is_empty() {
+            levels.sort();
+        }
+        Some(levels)
+    }
+
+    /// 当前锚点下某灰度值的区段序号与输出值（无锚点时返回None）
+    pub fn inspect_gray(&self, gray_value: u8) -> Option<(usize, u8)> {
+        if self.slider_values.is_empty() {
+            return None;
+        }
+        let output = match self.reflection_mode {
+            ReflectionMode::Average => ImageProcessor::segment_output(gray_value, &self.slider_values),
+            ReflectionMode::Partial => ImageProcessor::segment_output_partial(gray_value, &self.slider_values),
+        };
+        Some((ImageProcessor::segment_index(gray_value, &self.slider_values), output))
+    }
 
-        let reflection = match self.last_reflection_mode.as_ref()? {
+    /// 将最近的锚点移到指定灰度值；尚无锚点时新建一个
+    pub fn place_anchor(&mut self, value: f32) {
+        match self.find_closest_slider(value) {
+            Some(idx) => self.slider_values[idx] = value,
+            None => {
+                self.slider_values = vec![value];
+                self.slider_amount = Some(1);
+                self.slider_amount_input = "1".to_string();
+            }
+        }
+    }
+
+    /// 按给定配置对原图做一次颜色反射
+    fn render(
+        original_img: &DynamicImage,
+        slider_values: &[f32],
+        reflection_mode: ReflectionMode,
+        grayscale_mode: GrayscaleMode,
+    ) -> DynamicImage {
+        match reflection_mode {
+            ReflectionMode::Average => {
+                ImageProcessor::apply_color_reflection_with_mode(original_img, slider_values, grayscale_mode)
+            }
+            ReflectionMode::Partial => {
+                ImageProcessor::apply_color_reflection_partial_with_mode(original_img, slider_values, grayscale_mode)
+            }
+        }
+    }
+
+    /// 给定锚点与模式下的输出灰度级
+    fn levels_for(values: &[f32], mode: ReflectionMode) -> Vec<u8> {
+        match mode {
+            ReflectionMode::Average => ImageProcessor::segment_levels(values),
+            ReflectionMode::Partial => ImageProcessor::segment_levels_partial(values),
+        }
+    }
+
+    /// 显示分区列表：从当前选区新建区域，或更新/删除已有区域
+    fn show_regions(&mut self, ui: &mut egui::Ui, grayscale_mode: &mut GrayscaleMode, mask: Option<&GrayImage>) {
+        ui.label("Regions:");
+        if self.regions.is_empty() {
+            ui.label("No regions: the anchors above apply to the whole image");
+        }
+
+        let mut remove: Option<usize> = None;
+        for (i, region) in self.regions.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut region.name).desired_width(120.0));
+                let selected = region.mask.pixels().filter(|p| p[0] != 0).count();
+                let total = (region.mask.width() * region.mask.height()).max(1) as f32;
+                ui.label(format!(
+                    "{} anchors, {}, {}, {:.1}% of image",
+                    region.slider_values.len(),
+                    Self::reflection_name(region.reflection_mode),
+                    Self::grayscale_name(region.grayscale_mode),
+                    selected as f32 * 100.0 / total
+                ));
+                if ui.button("Edit").on_hover_text("Load this region's settings into the sliders").clicked() {
+                    self.slider_values = region.slider_values.clone();
+                    self.slider_amount = Some(region.slider_values.len());
+                    self.slider_amount_input = region.slider_values.len().to_string();
+                    self.reflection_mode = region.reflection_mode;
+                    *grayscale_mode = region.grayscale_mode;
+                }
+                if ui.button("Update").on_hover_text("Store the current slider settings in this region").clicked() {
+                    region.slider_values = self.slider_values.clone();
+                    region.reflection_mode = self.reflection_mode;
+                    region.grayscale_mode = *grayscale_mode;
+                }
+                if ui.button("Delete").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.regions.remove(i);
+        }
+
+        let can_add = mask.is_some() && !self.slider_values.is_empty();
+        if ui
+            .add_enabled(can_add, egui::Button::new("Add Region From Selection"))
+            .on_disabled_hover_text("Make a selection and configure sliders first")
+            .clicked()
+        {
+            if let Some(mask) = mask {
+                self.regions.push(Region {
+                    name: format!("Region {}", self.regions.len() + 1),
+                    slider_values: self.slider_values.clone(),
+                    reflection_mode: self.reflection_mode,
+                    grayscale_mode: *grayscale_mode,
+                    mask: mask.clone(),
+                });
+            }
+        }
+    }
+
+    pub fn reflection_name(mode: ReflectionMode) -> &'static str {
+        match mode {
             ReflectionMode::Average => "Average",
             ReflectionMode::Partial => "Partial",
-        };
-        let gray = match grayscale_mode {
+        }
+    }
+
+    fn grayscale_name(mode: GrayscaleMode) -> &'static str {
+        match mode {
             GrayscaleMode::Default => "Default",
             GrayscaleMode::Max => "Max",
             GrayscaleMode::Min => "Min",
-        };
-
-        // 简单JSON（避免引入serde依赖）
-        let json = format!(
-            "{{\"anchors\":{},\"reflectionMode\":\"{}\",\"grayscaleMode\":\"{}\"}}",
-            anchors, reflection, gray
-        );
-        Some(json)
+        }
     }
 
-    /// 解析JSON字符串中的 anchors 数组（不依赖serde）
-    fn parse_anchors_from_json(json: &str) -> Option<Vec<f32>> {
-        // 查找 "anchors":[...]
-        let key = "\"anchors\"";
-        let idx = json.find(key)?;
-        let after = &json[idx + key.len()..];
-        let lb = after.find('[')?;
-        let after_lb = &after[lb + 1..];
-        let rb = after_lb.find(']')?;
-        let inside = &after_lb[..rb];
-        let mut vals = Vec::new();
-        for part in inside.split(',') {
-            let t = part.trim();
-            if t.is_empty() { continue; }
-            if let Ok(v) = t.parse::<f32>() { vals.push(v); }
+    /// 构造包含锚点与模式信息的元数据JSON
+    pub fn build_anchors_metadata_json(&self, grayscale_mode: &GrayscaleMode) -> Option<String> {
+        if !self.has_applied_reflection {
+            return None;
         }
-        if vals.is_empty() { None } else { Some(vals) }
+        let anchors = self.last_applied_slider_values.clone()?;
+        let regions = self
+            .last_applied_regions
+            .iter()
+            .map(|r| RegionMetadata {
+                name: r.name.clone(),
+                anchors: r.slider_values.clone(),
+                reflection_mode: r.reflection_mode,
+                grayscale_mode: r.grayscale_mode,
+                mask: ImageProcessor::encode_mask_rle(&r.mask),
+            })
+            .collect();
+        let metadata = AnchorsMetadata::new(
+            anchors,
+            self.last_reflection_mode?,
+            *grayscale_mode,
+            self.applied_levels().unwrap_or_default(),
+            regions,
+        );
+        Some(metadata.to_json())
     }
 
-    /// 解析 grayscaleMode 字段
-    fn parse_grayscale_from_json(json: &str) -> Option<crate::utils::GrayscaleMode> {
-        let key = "\"grayscaleMode\"";
-        let idx = json.find(key)?;
-        let after = &json[idx + key.len()..];
-        let q1 = after.find('"')?; // 开始引号
-        let rest = &after[q1 + 1..];
-        let q2 = rest.find('"')?; // 结束引号
-        let val = &rest[..q2];
-        match val {
-            "Default" => Some(crate::utils::GrayscaleMode::Default),
-            "Max" => Some(crate::utils::GrayscaleMode::Max),
-            "Min" => Some(crate::utils::GrayscaleMode::Min),
-            _ => None,
-        }
+    /// 将读取到的元数据载入滑动条、反射模式与分区；蒙版损坏的区域会被跳过
+    fn apply_metadata(
+        &mut self,
+        metadata: AnchorsMetadata,
+        image_size: Option<(u32, u32)>,
+        grayscale_mode: &mut GrayscaleMode,
+    ) {
+        self.slider_amount = Some(metadata.anchors.len());
+        self.slider_amount_input = metadata.anchors.len().to_string();
+        self.slider_values = metadata.anchors;
+        self.reflection_mode = metadata.reflection_mode;
+        *grayscale_mode = metadata.grayscale_mode;
+        self.regions = metadata
+            .regions
+            .into_iter()
+            .filter_map(|r| match image_size.and_then(|size| ImageProcessor::decode_mask_rle(&r.mask, size)) {
+                Some(mask) => Some(Region {
+                    name: r.name,
+                    slider_values: r.anchors,
+                    reflection_mode: r.reflection_mode,
+                    grayscale_mode: r.grayscale_mode,
+                    mask,
+                }),
+                None => {
+                    notifications::warn(format!("Skipping region '{}': its mask could not be decoded or does not match the image size", r.name));
+                    None
+                }
+            })
+            .collect();
     }
 }
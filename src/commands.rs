@@ -0,0 +1,517 @@
+use crate::utils::ImageProcessor;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// 可通过快捷键或命令面板执行的操作
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Command {
+    OpenImage,
+    PasteImage,
+    CopyResult,
+    FastSave,
+    SaveWithAnchors,
+    SaveAs,
+    ExportIndexed,
+    Exit,
+    Undo,
+    Redo,
+    TogglePixelTools,
+    ToggleSelectionTools,
+    SelectAll,
+    InvertSelection,
+    Deselect,
+    SaveMask,
+    LoadMask,
+    ZoomIn,
+    ZoomOut,
+    FitToWindow,
+    FillWindow,
+    ActualSize,
+    ToggleOriginal,
+    CompareOff,
+    CompareSideBySide,
+    CompareSplit,
+    TogglePixelGrid,
+    TogglePixelInspector,
+    ToggleNotificationLog,
+    RestoreOriginal,
+    BlackAndWhite,
+    Clean,
+    ColorReflection,
+    ApplyColorReflection,
+    LoadAnchors,
+    FitToLoom,
+    Statistics,
+    MetadataInspector,
+    ToggleRepeatPreview,
+    BakeRepeatUnit,
+    ShowCommandPalette,
+    WriteKeyBindings,
+    ReloadKeyBindings,
+    ResetSettings,
+}
+
+impl Command {
+    pub const ALL: &'static [Command] = &[
+        Command::OpenImage,
+        Command::PasteImage,
+        Command::CopyResult,
+        Command::FastSave,
+        Command::SaveWithAnchors,
+        Command::SaveAs,
+        Command::ExportIndexed,
+        Command::Exit,
+        Command::Undo,
+        Command::Redo,
+        Command::TogglePixelTools,
+        Command::ToggleSelectionTools,
+        Command::SelectAll,
+        Command::InvertSelection,
+        Command::Deselect,
+        Command::SaveMask,
+        Command::LoadMask,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::FitToWindow,
+        Command::FillWindow,
+        Command::ActualSize,
+        Command::ToggleOriginal,
+        Command::CompareOff,
+        Command::CompareSideBySide,
+        Command::CompareSplit,
+        Command::TogglePixelGrid,
+        Command::TogglePixelInspector,
+        Command::ToggleNotificationLog,
+        Command::RestoreOriginal,
+        Command::BlackAndWhite,
+        Command::Clean,
+        Command::ColorReflection,
+        Command::ApplyColorReflection,
+        Command::LoadAnchors,
+        Command::FitToLoom,
+        Command::Statistics,
+        Command::MetadataInspector,
+        Command::ToggleRepeatPreview,
+        Command::BakeRepeatUnit,
+        Command::ShowCommandPalette,
+        Command::WriteKeyBindings,
+        Command::ReloadKeyBindings,
+        Command::ResetSettings,
+    ];
+
+    /// 快捷键文件中使用的标识
+    pub fn id(&self) -> &'static str {
+        match self {
+            Command::OpenImage => "open_image",
+            Command::PasteImage => "paste_image",
+            Command::CopyResult => "copy_result",
+            Command::FastSave => "fast_save",
+            Command::SaveWithAnchors => "save_with_anchors",
+            Command::SaveAs => "save_as",
+            Command::ExportIndexed => "export_indexed",
+            Command::Exit => "exit",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::TogglePixelTools => "toggle_pixel_tools",
+            Command::ToggleSelectionTools => "toggle_selection_tools",
+            Command::SelectAll => "select_all",
+            Command::InvertSelection => "invert_selection",
+            Command::Deselect => "deselect",
+            Command::SaveMask => "save_mask",
+            Command::LoadMask => "load_mask",
+            Command::ZoomIn => "zoom_in",
+            Command::ZoomOut => "zoom_out",
+            Command::FitToWindow => "fit_to_window",
+            Command::FillWindow => "fill_window",
+            Command::ActualSize => "actual_size",
+            Command::ToggleOriginal => "toggle_original",
+            Command::CompareOff => "compare_off",
+            Command::CompareSideBySide => "compare_side_by_side",
+            Command::CompareSplit => "compare_split",
+            Command::TogglePixelGrid => "toggle_pixel_grid",
+            Command::TogglePixelInspector => "toggle_pixel_inspector",
+            Command::ToggleNotificationLog => "toggle_notification_log",
+            Command::RestoreOriginal => "restore_original",
+            Command::BlackAndWhite => "black_and_white",
+            Command::Clean => "clean",
+            Command::ColorReflection => "color_reflection",
+            Command::ApplyColorReflection => "apply_color_reflection",
+            Command::LoadAnchors => "load_anchors",
+            Command::FitToLoom => "fit_to_loom",
+            Command::Statistics => "statistics",
+            Command::MetadataInspector => "metadata_inspector",
+            Command::ToggleRepeatPreview => "toggle_repeat_preview",
+            Command::BakeRepeatUnit => "bake_repeat_unit",
+            Command::ShowCommandPalette => "command_palette",
+            Command::WriteKeyBindings => "write_key_bindings",
+            Command::ReloadKeyBindings => "reload_key_bindings",
+            Command::ResetSettings => "reset_settings",
+        }
+    }
+
+    /// 界面上显示的名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Command::OpenImage => "Open Image",
+            Command::PasteImage => "Paste Image as New",
+            Command::CopyResult => "Copy Result to Clipboard",
+            Command::FastSave => "Fast Save",
+            Command::SaveWithAnchors => "Save With Anchors",
+            Command::SaveAs => "Save As...",
+            Command::ExportIndexed => "Export Indexed...",
+            Command::Exit => "Exit",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::TogglePixelTools => "Toggle Pixel Editing Tools",
+            Command::ToggleSelectionTools => "Toggle Selection Tools",
+            Command::SelectAll => "Select All",
+            Command::InvertSelection => "Invert Selection",
+            Command::Deselect => "Deselect",
+            Command::SaveMask => "Save Mask...",
+            Command::LoadMask => "Load Mask...",
+            Command::ZoomIn => "Zoom In",
+            Command::ZoomOut => "Zoom Out",
+            Command::FitToWindow => "Fit to Window",
+            Command::FillWindow => "Fill Window",
+            Command::ActualSize => "Actual Size (100%)",
+            Command::ToggleOriginal => "Toggle Original View",
+            Command::CompareOff => "Compare: Off",
+            Command::CompareSideBySide => "Compare: Side by Side",
+            Command::CompareSplit => "Compare: Split Wipe",
+            Command::TogglePixelGrid => "Toggle Pixel Grid",
+            Command::TogglePixelInspector => "Toggle Pixel Inspector",
+            Command::ToggleNotificationLog => "Notification Log",
+            Command::RestoreOriginal => "Restore Original Image",
+            Command::BlackAndWhite => "Black & White",
+            Command::Clean => "Clean",
+            Command::ColorReflection => "Color Reflection...",
+            Command::ApplyColorReflection => "Apply Color Reflection",
+            Command::LoadAnchors => "Load Anchors From PNG",
+            Command::FitToLoom => "Fit to Loom...",
+            Command::Statistics => "Level Statistics...",
+            Command::MetadataInspector => "PNG Metadata...",
+            Command::ToggleRepeatPreview => "Toggle Repeat Preview",
+            Command::BakeRepeatUnit => "Bake Repeat Unit",
+            Command::ShowCommandPalette => "Command Palette",
+            Command::WriteKeyBindings => "Write Key Bindings File",
+            Command::ReloadKeyBindings => "Reload Key Bindings File",
+            Command::ResetSettings => "Reset Settings to Defaults",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Command> {
+        Command::ALL.iter().copied().find(|c| c.id() == id)
+    }
+
+    /// 默认快捷键
+    fn default_shortcut(&self) -> Option<egui::KeyboardShortcut> {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+        let cmd = Modifiers::COMMAND;
+        let cmd_shift = Modifiers::COMMAND | Modifiers::SHIFT;
+        let shortcut = match self {
+            Command::OpenImage => KeyboardShortcut::new(cmd, Key::O),
+            Command::FastSave => KeyboardShortcut::new(cmd, Key::S),
+            Command::SaveWithAnchors => KeyboardShortcut::new(cmd_shift, Key::S),
+            Command::SaveAs => KeyboardShortcut::new(cmd | Modifiers::ALT, Key::S),
+            Command::ExportIndexed => KeyboardShortcut::new(cmd, Key::E),
+            Command::Exit => KeyboardShortcut::new(cmd, Key::Q),
+            Command::Undo => KeyboardShortcut::new(cmd, Key::Z),
+            Command::Redo => KeyboardShortcut::new(cmd_shift, Key::Z),
+            Command::SelectAll => KeyboardShortcut::new(cmd, Key::A),
+            Command::InvertSelection => KeyboardShortcut::new(cmd_shift, Key::I),
+            Command::Deselect => KeyboardShortcut::new(cmd, Key::D),
+            Command::ZoomIn => KeyboardShortcut::new(cmd, Key::Equals),
+            Command::ZoomOut => KeyboardShortcut::new(cmd, Key::Minus),
+            Command::FitToWindow => KeyboardShortcut::new(cmd, Key::Num0),
+            Command::ActualSize => KeyboardShortcut::new(cmd, Key::Num1),
+            Command::ToggleOriginal => KeyboardShortcut::new(Modifiers::NONE, Key::Backslash),
+            Command::TogglePixelGrid => KeyboardShortcut::new(cmd, Key::G),
+            Command::ColorReflection => KeyboardShortcut::new(cmd, Key::R),
+            Command::ShowCommandPalette => KeyboardShortcut::new(cmd_shift, Key::P),
+            _ => return None,
+        };
+        Some(shortcut)
+    }
+}
+
+/// 命令与快捷键的对应关系（可由用户文件覆盖）
+pub struct KeyBindings {
+    bindings: HashMap<Command, egui::KeyboardShortcut>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = Command::ALL
+            .iter()
+            .filter_map(|&c| c.default_shortcut().map(|s| (c, s)))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// 快捷键文件位置（<配置目录>/weave_tool_egui/keybindings.txt）
+    pub fn file_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("weave_tool_egui").join("keybindings.txt"))
+    }
+
+    /// 读取默认快捷键并叠加用户文件中的设置
+    pub fn load() -> Result<Self, String> {
+        let mut bindings = Self::default();
+        let Some(path) = Self::file_path().filter(|p| p.exists()) else {
+            return Ok(bindings);
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        bindings.apply_text(&text)?;
+        Ok(bindings)
+    }
+
+    /// 解析快捷键文件：每行 `命令 = 快捷键`，快捷键留空表示取消绑定，#开头为注释
+    fn apply_text(&mut self, text: &str) -> Result<(), String> {
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (id, shortcut) = line
+                .split_once('=')
+                .ok_or_else(|| format!("Line {}: expected `command = shortcut`", line_no + 1))?;
+            let command = Command::from_id(id.trim())
+                .ok_or_else(|| format!("Line {}: unknown command '{}'", line_no + 1, id.trim()))?;
+            let shortcut = shortcut.trim();
+            if shortcut.is_empty() {
+                self.bindings.remove(&command);
+            } else {
+                let parsed = Self::parse_shortcut(shortcut)
+                    .ok_or_else(|| format!("Line {}: invalid shortcut '{}'", line_no + 1, shortcut))?;
+                self.bindings.insert(command, parsed);
+            }
+        }
+        Ok(())
+    }
+
+    /// 把当前设置写入快捷键文件（列出全部命令，便于编辑）
+    pub fn write_file(&self) -> Result<PathBuf, String> {
+        let path = Self::file_path().ok_or("No configuration directory available")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let mut text = String::from(
+            "# Key bindings: `command = shortcut`, e.g. `fast_save = Ctrl+S`.\n\
+             # Modifiers: Ctrl (Cmd on macOS), Shift, Alt. Leave the shortcut empty to unbind.\n",
+        );
+        for command in Command::ALL {
+            let shortcut = self.bindings.get(command).map(Self::format_shortcut).unwrap_or_default();
+            text.push_str(&format!("{} = {}\n", command.id(), shortcut));
+        }
+        ImageProcessor::write_bytes_atomic(&path, text.as_bytes()).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+
+    /// 解析形如 `Ctrl+Shift+P` 的快捷键
+    fn parse_shortcut(text: &str) -> Option<egui::KeyboardShortcut> {
+        let mut modifiers = egui::Modifiers::NONE;
+        let mut key = None;
+        for part in text.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "cmd" | "command" => modifiers |= egui::Modifiers::COMMAND,
+                "shift" => modifiers |= egui::Modifiers::SHIFT,
+                "alt" | "option" => modifiers |= egui::Modifiers::ALT,
+                _ if key.is_none() => key = Some(egui::Key::from_name(part)?),
+                _ => return None,
+            }
+        }
+        Some(egui::KeyboardShortcut::new(modifiers, key?))
+    }
+
+    fn format_shortcut(shortcut: &egui::KeyboardShortcut) -> String {
+        let mut parts = Vec::new();
+        if shortcut.modifiers.command || shortcut.modifiers.ctrl {
+            parts.push("Ctrl");
+        }
+        if shortcut.modifiers.shift {
+            parts.push("Shift");
+        }
+        if shortcut.modifiers.alt {
+            parts.push("Alt");
+        }
+        parts.push(shortcut.logical_key.name());
+        parts.join("+")
+    }
+
+    /// 菜单与命令面板中显示的快捷键文字
+    pub fn shortcut_text(&self, ctx: &egui::Context, command: Command) -> String {
+        self.bindings
+            .get(&command)
+            .map(|s| ctx.format_shortcut(s))
+            .unwrap_or_default()
+    }
+
+    /// 本帧按下的快捷键对应的命令（正在输入文字时不响应）
+    pub fn triggered(&self, ctx: &egui::Context) -> Option<Command> {
+        if ctx.wants_keyboard_input() {
+            return None;
+        }
+        // 修饰键多的优先匹配，避免 Ctrl+Shift+S 被 Ctrl+S 抢先
+        let mut bindings: Vec<(&Command, &egui::KeyboardShortcut)> = self.bindings.iter().collect();
+        bindings.sort_by_key(|(_, s)| {
+            std::cmp::Reverse(s.modifiers.shift as u8 + s.modifiers.alt as u8 + s.modifiers.command as u8)
+        });
+        let command = bindings
+            .into_iter()
+            .find(|(_, s)| ctx.input_mut(|i| i.consume_shortcut(s)))
+            .map(|(c, _)| *c);
+        // 系统的复制/粘贴快捷键被egui转换为Copy/Paste事件，不会产生按键事件
+        command.or_else(|| {
+            ctx.input(|i| {
+                i.events.iter().find_map(|e| match e {
+                    egui::Event::Copy => Some(Command::CopyResult),
+                    egui::Event::Paste(_) => Some(Command::PasteImage),
+                    _ => None,
+                })
+            })
+        })
+    }
+}
+
+/// 可搜索的命令面板
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// 显示面板，返回被选中执行的命令
+    pub fn show(&mut self, ctx: &egui::Context, bindings: &KeyBindings) -> Option<Command> {
+        if !self.open {
+            return None;
+        }
+        let query = self.query.to_lowercase();
+        let matches: Vec<Command> = Command::ALL
+            .iter()
+            .copied()
+            .filter(|c| c.label().to_lowercase().contains(&query) || c.id().contains(&query))
+            .collect();
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let (up, down, enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down && self.selected + 1 < matches.len() {
+            self.selected += 1;
+        }
+
+        let mut chosen = None;
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([420.0, 320.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, &command) in matches.iter().enumerate() {
+                        let button = egui::Button::selectable(i == self.selected, command.label())
+                            .shortcut_text(bindings.shortcut_text(ctx, command));
+                        let item = ui.add_sized([ui.available_width(), 20.0], button);
+                        if i == self.selected && (up || down) {
+                            item.scroll_to_me(None);
+                        }
+                        if item.clicked() {
+                            chosen = Some(command);
+                        }
+                    }
+                });
+            });
+
+        if enter {
+            chosen = chosen.or(matches.get(self.selected).copied());
+        }
+        if chosen.is_some() || escape {
+            self.open = false;
+        }
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_shortcuts_round_trip_through_text() {
+        let defaults = KeyBindings::default();
+        for (command, shortcut) in &defaults.bindings {
+            let text = KeyBindings::format_shortcut(shortcut);
+            assert_eq!(KeyBindings::parse_shortcut(&text), Some(*shortcut), "{:?} as '{}'", command, text);
+        }
+        let mut bindings = KeyBindings { bindings: HashMap::new() };
+        let text: String = Command::ALL
+            .iter()
+            .filter_map(|c| defaults.bindings.get(c).map(|s| format!("{} = {}\n", c.id(), KeyBindings::format_shortcut(s))))
+            .collect();
+        bindings.apply_text(&text).unwrap();
+        assert_eq!(bindings.bindings, defaults.bindings);
+    }
+
+    #[test]
+    fn parses_modifiers_in_any_order_and_case() {
+        let expected = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::P);
+        assert_eq!(KeyBindings::parse_shortcut("Ctrl+Shift+P"), Some(expected));
+        assert_eq!(KeyBindings::parse_shortcut(" shift + cmd + P "), Some(expected));
+    }
+
+    #[test]
+    fn empty_value_unbinds_and_comments_are_skipped() {
+        let mut bindings = KeyBindings::default();
+        assert!(bindings.bindings.contains_key(&Command::FastSave));
+        bindings.apply_text("# comment\n\nfast_save =\n").unwrap();
+        assert!(!bindings.bindings.contains_key(&Command::FastSave));
+        assert!(bindings.bindings.contains_key(&Command::OpenImage));
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_bad_lines() {
+        let mut bindings = KeyBindings::default();
+        let err = bindings.apply_text("fast_save = Ctrl+S\nlaunch_rockets = Ctrl+L\n").unwrap_err();
+        assert!(err.starts_with("Line 2:"), "{}", err);
+        assert!(err.contains("launch_rockets"));
+        assert!(bindings.apply_text("fast_save Ctrl+S").unwrap_err().contains("expected"));
+    }
+
+    #[test]
+    fn rejects_bad_keys() {
+        assert_eq!(KeyBindings::parse_shortcut("Ctrl+NoSuchKey"), None);
+        assert_eq!(KeyBindings::parse_shortcut("Ctrl+S+T"), None);
+        assert_eq!(KeyBindings::parse_shortcut("Ctrl+Shift"), None);
+        assert_eq!(KeyBindings::parse_shortcut(""), None);
+        let mut bindings = KeyBindings::default();
+        assert!(bindings.apply_text("fast_save = Hyper+S").unwrap_err().contains("invalid shortcut"));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::utils::ImageProcessor;
+use crate::view_transform::ViewTransform;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// 原图与处理结果对比视图的状态（只读显示，不修改任何图像）
+pub struct CompareView {
+    pub mode: CompareMode,
+    // 按住该键时临时显示原图
+    pub flicker_key: egui::Key,
+    // 常显原图（由快捷键切换），与按住按键效果相同
+    pub show_original: bool,
+    // 分割线位置（相对图像宽度，0-1）
+    pub split: f32,
+    original_texture: Option<egui::TextureHandle>,
+}
+
+/// 对比方式
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CompareMode {
+    Off,
+    SideBySide,
+    Split,
+}
+
+impl CompareMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CompareMode::Off => "Off",
+            CompareMode::SideBySide => "Side by Side",
+            CompareMode::Split => "Split Wipe",
+        }
+    }
+}
+
+impl Default for CompareView {
+    fn default() -> Self {
+        Self {
+            mode: CompareMode::Off,
+            flicker_key: egui::Key::Space,
+            show_original: false,
+            split: 0.5,
+            original_texture: None,
+        }
+    }
+}
+
+impl CompareView {
+    /// 原图被替换后丢弃缓存的纹理
+    pub fn invalidate(&mut self) {
+        self.original_texture = None;
+    }
+
+    /// 按需为原图生成纹理
+    fn original_texture(
+        &mut self,
+        ctx: &egui::Context,
+        original_image: &Option<DynamicImage>,
+    ) -> Option<&egui::TextureHandle> {
+        if self.original_texture.is_none() {
+            let img = original_image.as_ref()?;
+            self.original_texture = Some(ImageProcessor::update_texture_from_image(img, ctx));
+        }
+        self.original_texture.as_ref()
+    }
+
+    /// 是否正按住闪烁对比键（文本输入时不响应）
+    pub fn flicker_active(&self, ctx: &egui::Context) -> bool {
+        !ctx.wants_keyboard_input() && ctx.input(|i| i.key_down(self.flicker_key))
+    }
+
+    /// 闪烁对比：按住按键（或切换为常显）时用原图覆盖处理结果
+    pub fn paint_flicker(
+        &mut self,
+        ui: &egui::Ui,
+        image_rect: egui::Rect,
+        original_image: &Option<DynamicImage>,
+    ) {
+        if !self.show_original && !self.flicker_active(ui.ctx()) {
+            return;
+        }
+        if let Some(texture) = self.original_texture(ui.ctx(), original_image) {
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            ui.painter().image(texture.id(), image_rect, uv, egui::Color32::WHITE);
+        }
+    }
+
+    /// 分割对比：分割线左侧显示原图，右侧显示处理结果，分割线可拖动
+    pub fn paint_split(
+        &mut self,
+        ui: &mut egui::Ui,
+        image_rect: egui::Rect,
+        original_image: &Option<DynamicImage>,
+    ) {
+        let split_x = image_rect.min.x + image_rect.width() * self.split;
+        let left = egui::Rect::from_min_max(image_rect.min, egui::pos2(split_x, image_rect.max.y));
+        let clip = ui.clip_rect().intersect(left);
+        if let Some(texture) = self.original_texture(ui.ctx(), original_image) {
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            ui.painter_at(clip)
+                .image(texture.id(), image_rect, uv, egui::Color32::WHITE);
+        }
+
+        // 分割线与拖动手柄（后分配，优先于图像的拖拽平移）
+        let visible = image_rect.intersect(ui.clip_rect());
+        let handle = egui::Rect::from_center_size(
+            egui::pos2(split_x, visible.center().y),
+            egui::vec2(12.0, visible.height().max(0.0)),
+        );
+        let response = ui
+            .allocate_rect(handle, egui::Sense::drag())
+            .on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+        if response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.split = ((pos.x - image_rect.min.x) / image_rect.width()).clamp(0.0, 1.0);
+            }
+        }
+        let painter = ui.painter();
+        painter.line_segment(
+            [egui::pos2(split_x, visible.min.y), egui::pos2(split_x, visible.max.y)],
+            egui::Stroke::new(2.0, egui::Color32::WHITE),
+        );
+        painter.circle_filled(egui::pos2(split_x, visible.center().y), 6.0, egui::Color32::WHITE);
+        painter.text(
+            egui::pos2(split_x - 6.0, visible.min.y + 4.0),
+            egui::Align2::RIGHT_TOP,
+            "Original",
+            egui::FontId::proportional(12.0),
+            egui::Color32::WHITE,
+        );
+        painter.text(
+            egui::pos2(split_x + 6.0, visible.min.y + 4.0),
+            egui::Align2::LEFT_TOP,
+            "Processed",
+            egui::FontId::proportional(12.0),
+            egui::Color32::WHITE,
+        );
+    }
+
+    /// 并排对比：左原图右结果，两侧共用缩放与平移
+    pub fn show_side_by_side(
+        &mut self,
+        ui: &mut egui::Ui,
+        processed: &egui::TextureHandle,
+        original_image: &Option<DynamicImage>,
+        view: &mut ViewTransform,
+    ) {
+        let panel = ui.max_rect();
+        let (left, right) = panel.split_left_right_at_fraction(0.5);
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+
+        let response = ui.allocate_rect(panel, egui::Sense::click_and_drag());
+        if response.dragged() {
+            view.pan += ui.input(|i| i.pointer.delta());
+        }
+
+        let original_id = self.original_texture(ui.ctx(), original_image).map(|t| t.id());
+        for (half, texture_id, label) in [
+            (left, original_id, "Original"),
+            (right, Some(processed.id()), "Processed"),
+        ] {
+            // 每一半作为独立的显示区域，缩放与平移沿用主视图
+            let half_view = ViewTransform { viewport: half, ..*view };
+            let image_rect = half_view.image_rect(processed.size_vec2());
+            let painter = ui.painter_at(half);
+            if let Some(id) = texture_id {
+                painter.image(id, image_rect, uv, egui::Color32::WHITE);
+            }
+            painter.text(
+                half.min + egui::vec2(8.0, 6.0),
+                egui::Align2::LEFT_TOP,
+                label,
+                egui::FontId::proportional(13.0),
+                egui::Color32::WHITE,
+            );
+        }
+        ui.painter().line_segment(
+            [left.right_top(), left.right_bottom()],
+            egui::Stroke::new(2.0, egui::Color32::from_gray(60)),
+        );
+    }
+}
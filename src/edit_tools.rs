@@ -0,0 +1,443 @@
+use crate::notifications;
+use crate::utils::ImageProcessor;
+use image::{DynamicImage, GrayImage, RgbaImage};
+use std::path::PathBuf;
+
+/// 像素编辑工具的状态
+pub struct EditTools {
+    pub show_toolbar: bool,
+    pub tool: EditTool,
+    pub brush_size: u32,
+    pub fill_rect: bool,
+    pub selected_level: usize,
+    // 当前处理结果的内存副本，与显示纹理同步
+    working: Option<RgbaImage>,
+    working_texture: Option<egui::TextureId>,
+    // 工作图像中出现的颜色（无锚点时作为调色板）
+    image_palette: Vec<[u8; 3]>,
+    undo_stack: Vec<RgbaImage>,
+    redo_stack: Vec<RgbaImage>,
+    // 当前拖拽的起点与上一点（像素坐标）
+    drag_start: Option<(i64, i64)>,
+    last_point: Option<(i64, i64)>,
+}
+
+/// 编辑工具
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditTool {
+    Pencil,
+    Line,
+    Rectangle,
+    Fill,
+    ReplaceLevel,
+}
+
+impl EditTool {
+    fn label(&self) -> &'static str {
+        match self {
+            EditTool::Pencil => "Pencil",
+            EditTool::Line => "Line",
+            EditTool::Rectangle => "Rectangle",
+            EditTool::Fill => "Fill",
+            EditTool::ReplaceLevel => "Replace Level",
+        }
+    }
+}
+
+impl Default for EditTools {
+    fn default() -> Self {
+        Self {
+            show_toolbar: false,
+            tool: EditTool::Pencil,
+            brush_size: 1,
+            fill_rect: false,
+            selected_level: 0,
+            working: None,
+            working_texture: None,
+            image_palette: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            drag_start: None,
+            last_point: None,
+        }
+    }
+}
+
+impl EditTools {
+    // 撤销历史占用内存的上限（字节），至少保留一步
+    const MAX_UNDO_BYTES: usize = 256 * 1024 * 1024;
+    // 超过该颜色数视为未分级的图像，不提供调色板
+    const MAX_IMAGE_PALETTE: usize = 64;
+
+    /// 编辑工具是否正在接管主显示区域的左键
+    pub fn is_active(&self) -> bool {
+        self.show_toolbar && self.working.is_some()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// 切换文档时清空工作副本与历史
+    pub fn reset(&mut self) {
+        self.working = None;
+        self.working_texture = None;
+        self.image_palette.clear();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.drag_start = None;
+        self.last_point = None;
+    }
+
+    /// 每帧同步：当前纹理被其他操作替换时重新读取临时文件
+    ///
+    /// 外部操作不经过本模块的撤销栈，旧的编辑历史无法再对应新的图像，因此清空
+    pub fn sync(&mut self, current_texture: &Option<egui::TextureHandle>, temp_path: &Option<PathBuf>) {
+        let Some(texture) = current_texture else {
+            return;
+        };
+        if self.working_texture == Some(texture.id()) {
+            return;
+        }
+        let Some(img) = temp_path.as_ref().and_then(|p| image::open(p).ok()) else {
+            return;
+        };
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.set_working(img.to_rgba8());
+        self.working_texture = Some(texture.id());
+    }
+
+    fn set_working(&mut self, img: RgbaImage) {
+        let dynamic = DynamicImage::ImageRgba8(img);
+        self.image_palette = match ImageProcessor::image_levels(&dynamic) {
+            Ok(levels) if levels.len() <= Self::MAX_IMAGE_PALETTE => levels,
+            _ => Vec::new(),
+        };
+        self.working = Some(dynamic.into_rgba8());
+    }
+
+    fn push_undo(&mut self, img: RgbaImage) {
+        self.undo_stack.push(img);
+        self.trim_undo();
+        self.redo_stack.clear();
+    }
+
+    /// 按总字节数丢弃最旧的撤销步骤
+    fn trim_undo(&mut self) {
+        let mut total: usize = self.undo_stack.iter().map(|img| img.as_raw().len()).sum();
+        while total > Self::MAX_UNDO_BYTES && self.undo_stack.len() > 1 {
+            total -= self.undo_stack.remove(0).as_raw().len();
+        }
+    }
+
+    /// 可用的调色板：优先使用最近一次反射的区段输出值
+    fn palette(&self, anchor_levels: &Option<Vec<u8>>) -> Vec<[u8; 3]> {
+        match anchor_levels {
+            Some(levels) if !levels.is_empty() => levels.iter().map(|&v| [v, v, v]).collect(),
+            _ => self.image_palette.clone(),
+        }
+    }
+
+    /// 撤销
+    pub fn undo(&mut self, current_texture: &mut Option<egui::TextureHandle>, temp_path: &Option<PathBuf>, revision: &mut u64) {
+        if let Some(previous) = self.undo_stack.pop() {
+            if let Some(current) = self.working.take() {
+                self.redo_stack.push(current);
+            }
+            self.set_working(previous);
+            self.commit(current_texture, temp_path, revision);
+        }
+    }
+
+    /// 重做
+    pub fn redo(&mut self, current_texture: &mut Option<egui::TextureHandle>, temp_path: &Option<PathBuf>, revision: &mut u64) {
+        if let Some(next) = self.redo_stack.pop() {
+            if let Some(current) = self.working.take() {
+                self.undo_stack.push(current);
+                self.trim_undo();
+            }
+            self.set_working(next);
+            self.commit(current_texture, temp_path, revision);
+        }
+    }
+
+    /// 刷新纹理并写回临时文件
+    fn commit(&mut self, current_texture: &mut Option<egui::TextureHandle>, temp_path: &Option<PathBuf>, revision: &mut u64) {
+        self.refresh(current_texture);
+        if let (Some(working), Some(temp_path)) = (&self.working, temp_path) {
+            let img = DynamicImage::ImageRgba8(working.clone());
+            if let Err(e) = ImageProcessor::save_to_temp(&img, temp_path, revision) {
+                notifications::error(format!("Failed to save to temp file: {}", e));
+            }
+        }
+    }
+
+    /// 仅刷新纹理（拖拽过程中使用）
+    fn refresh(&mut self, current_texture: &mut Option<egui::TextureHandle>) {
+        if let (Some(working), Some(texture)) = (&self.working, current_texture.as_mut()) {
+            ImageProcessor::refresh_texture(texture, &DynamicImage::ImageRgba8(working.clone()));
+            self.working_texture = Some(texture.id());
+        }
+    }
+
+    /// 显示编辑工具栏与调色板条
+    pub fn show_toolbar(
+        &mut self,
+        ctx: &egui::Context,
+        current_texture: &mut Option<egui::TextureHandle>,
+        temp_path: &Option<PathBuf>,
+        revision: &mut u64,
+        anchor_levels: &Option<Vec<u8>>,
+    ) {
+        if !self.show_toolbar {
+            return;
+        }
+        let palette = self.palette(anchor_levels);
+        egui::TopBottomPanel::top("edit_toolbar").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for tool in [
+                    EditTool::Pencil,
+                    EditTool::Line,
+                    EditTool::Rectangle,
+                    EditTool::Fill,
+                    EditTool::ReplaceLevel,
+                ] {
+                    ui.selectable_value(&mut self.tool, tool, tool.label());
+                }
+                ui.separator();
+                ui.label("Brush");
+                ui.add(egui::DragValue::new(&mut self.brush_size).range(1..=64).suffix(" px"));
+                if self.tool == EditTool::Rectangle {
+                    ui.checkbox(&mut self.fill_rect, "Filled");
+                }
+                ui.separator();
+                if ui.add_enabled(self.can_undo(), egui::Button::new("Undo")).clicked() {
+                    self.undo(current_texture, temp_path, revision);
+                }
+                if ui.add_enabled(self.can_redo(), egui::Button::new("Redo")).clicked() {
+                    self.redo(current_texture, temp_path, revision);
+                }
+            });
+
+            // 调色板条：索引N即第N级
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Levels:");
+                if palette.is_empty() {
+                    ui.label("Apply Color Reflection first to get output levels");
+                }
+                for (i, c) in palette.iter().enumerate() {
+                    let (rect, response) =
+                        ui.allocate_exact_size(egui::vec2(28.0, 20.0), egui::Sense::click());
+                    let color = egui::Color32::from_rgb(c[0], c[1], c[2]);
+                    ui.painter().rect_filled(rect, 2.0, color);
+                    let stroke = if i == self.selected_level {
+                        egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 150, 255))
+                    } else {
+                        egui::Stroke::new(1.0, egui::Color32::from_gray(120))
+                    };
+                    ui.painter().rect_stroke(rect, 2.0, stroke, egui::StrokeKind::Outside);
+                    let text_color = if c[0] as u32 + c[1] as u32 + c[2] as u32 > 384 {
+                        egui::Color32::BLACK
+                    } else {
+                        egui::Color32::WHITE
+                    };
+                    ui.painter().text(
+                        rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        i.to_string(),
+                        egui::FontId::proportional(10.0),
+                        text_color,
+                    );
+                    if response.on_hover_text(format!("Level {}: {:?}", i, c)).clicked() {
+                        self.selected_level = i;
+                    }
+                }
+            });
+        });
+        if self.selected_level >= palette.len() {
+            self.selected_level = 0;
+        }
+    }
+
+    /// 屏幕坐标转换为图像像素坐标
+    fn to_pixel(&self, pos: egui::Pos2, image_rect: egui::Rect) -> Option<(i64, i64)> {
+        let working = self.working.as_ref()?;
+        let rel = (pos - image_rect.min) / image_rect.size();
+        let x = (rel.x * working.width() as f32).floor() as i64;
+        let y = (rel.y * working.height() as f32).floor() as i64;
+        Some((x, y))
+    }
+
+    /// 选区外的像素恢复为编辑前的值
+    fn restrict(edited: &mut RgbaImage, base: &RgbaImage, mask: Option<&GrayImage>) {
+        let Some(mask) = mask else {
+            return;
+        };
+        if edited.dimensions() != mask.dimensions() || base.dimensions() != mask.dimensions() {
+            return;
+        }
+        for ((out, b), m) in edited.pixels_mut().zip(base.pixels()).zip(mask.pixels()) {
+            if m[0] == 0 {
+                *out = *b;
+            }
+        }
+    }
+
+    /// 以撤销栈顶（本次编辑前的图像）为基准应用选区限制
+    fn restrict_working(&mut self, mask: Option<&GrayImage>) {
+        if let (Some(working), Some(base)) = (self.working.as_mut(), self.undo_stack.last()) {
+            Self::restrict(working, base, mask);
+        }
+    }
+
+    /// 处理主显示区域上的左键编辑操作
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_input(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        image_rect: egui::Rect,
+        current_texture: &mut Option<egui::TextureHandle>,
+        temp_path: &Option<PathBuf>,
+        revision: &mut u64,
+        anchor_levels: &Option<Vec<u8>>,
+        mask: Option<&GrayImage>,
+    ) {
+        let palette = self.palette(anchor_levels);
+        let Some(c) = palette.get(self.selected_level) else {
+            return;
+        };
+        let color = image::Rgba([c[0], c[1], c[2], 255]);
+        let primary = egui::PointerButton::Primary;
+        let pointer = response
+            .interact_pointer_pos()
+            .or_else(|| ui.input(|i| i.pointer.hover_pos()));
+        let Some(point) = pointer.and_then(|p| self.to_pixel(p, image_rect)) else {
+            return;
+        };
+
+        match self.tool {
+            EditTool::Pencil => {
+                if response.drag_started_by(primary) || response.clicked() {
+                    if let Some(working) = self.working.clone() {
+                        self.push_undo(working);
+                    }
+                    if let Some(working) = self.working.as_mut() {
+                        ImageProcessor::stamp(working, point.0, point.1, self.brush_size, color);
+                    }
+                    self.restrict_working(mask);
+                    self.last_point = Some(point);
+                    self.refresh(current_texture);
+                } else if response.dragged_by(primary) {
+                    let from = self.last_point.unwrap_or(point);
+                    if let Some(working) = self.working.as_mut() {
+                        ImageProcessor::draw_line(working, from, point, self.brush_size, color);
+                    }
+                    self.restrict_working(mask);
+                    self.last_point = Some(point);
+                    self.refresh(current_texture);
+                }
+                if response.drag_stopped_by(primary) || response.clicked() {
+                    self.last_point = None;
+                    self.commit(current_texture, temp_path, revision);
+                }
+            }
+            EditTool::Line | EditTool::Rectangle => {
+                if response.drag_started_by(primary) {
+                    self.drag_start = Some(point);
+                }
+                if let Some(start) = self.drag_start {
+                    if response.drag_stopped_by(primary) {
+                        if let Some(working) = self.working.clone() {
+                            self.push_undo(working);
+                        }
+                        if let Some(working) = self.working.as_mut() {
+                            if self.tool == EditTool::Line {
+                                ImageProcessor::draw_line(working, start, point, self.brush_size, color);
+                            } else {
+                                ImageProcessor::draw_rect(
+                                    working,
+                                    start,
+                                    point,
+                                    self.brush_size,
+                                    self.fill_rect,
+                                    color,
+                                );
+                            }
+                        }
+                        self.restrict_working(mask);
+                        self.drag_start = None;
+                        self.commit(current_texture, temp_path, revision);
+                    } else {
+                        self.paint_preview(ui, image_rect, start, point, color);
+                    }
+                }
+            }
+            EditTool::Fill | EditTool::ReplaceLevel => {
+                if response.clicked() {
+                    let Some(working) = self.working.as_ref() else {
+                        return;
+                    };
+                    if point.0 < 0
+                        || point.1 < 0
+                        || point.0 >= working.width() as i64
+                        || point.1 >= working.height() as i64
+                    {
+                        return;
+                    }
+                    let (x, y) = (point.0 as u32, point.1 as u32);
+                    let mut edited = working.clone();
+                    if self.tool == EditTool::Fill {
+                        ImageProcessor::flood_fill(&mut edited, x, y, color);
+                    } else {
+                        let from = *edited.get_pixel(x, y);
+                        ImageProcessor::replace_color(&mut edited, from, color);
+                    }
+                    Self::restrict(&mut edited, working, mask);
+                    if let Some(previous) = self.working.replace(edited) {
+                        self.push_undo(previous);
+                    }
+                    self.commit(current_texture, temp_path, revision);
+                }
+            }
+        }
+    }
+
+    /// 拖拽直线/矩形时在屏幕上绘制预览
+    fn paint_preview(
+        &self,
+        ui: &egui::Ui,
+        image_rect: egui::Rect,
+        start: (i64, i64),
+        end: (i64, i64),
+        color: image::Rgba<u8>,
+    ) {
+        let Some(working) = self.working.as_ref() else {
+            return;
+        };
+        let scale = image_rect.size() / egui::vec2(working.width() as f32, working.height() as f32);
+        let center = |p: (i64, i64)| {
+            image_rect.min + egui::vec2((p.0 as f32 + 0.5) * scale.x, (p.1 as f32 + 0.5) * scale.y)
+        };
+        let stroke = egui::Stroke::new(
+            (self.brush_size as f32 * scale.x).max(1.0),
+            egui::Color32::from_rgb(color[0], color[1], color[2]),
+        );
+        let painter = ui.painter();
+        if self.tool == EditTool::Line {
+            painter.line_segment([center(start), center(end)], stroke);
+        } else {
+            let rect = egui::Rect::from_two_pos(center(start), center(end));
+            if self.fill_rect {
+                painter.rect_filled(rect, 0.0, stroke.color);
+            } else {
+                painter.rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Middle);
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::notifications;
+use crate::utils::ImageProcessor;
+use std::path::PathBuf;
+
+/// 索引图导出窗口的状态
+pub struct ExportWindow {
+    pub show_window: bool,
+    pub format: IndexedFormat,
+    pub palette_source: PaletteSource,
+}
+
+/// 导出格式
+#[derive(Clone, Copy, PartialEq)]
+pub enum IndexedFormat {
+    Png8,
+    Bmp8,
+    Png1,
+    Bmp1,
+}
+
+/// 调色板来源
+#[derive(Clone, Copy, PartialEq)]
+pub enum PaletteSource {
+    // 图像中实际出现的颜色，从暗到亮
+    ImageLevels,
+    // 最近一次Color Reflection的区段输出值
+    Anchors,
+}
+
+impl IndexedFormat {
+    fn label(&self) -> &'static str {
+        match self {
+            IndexedFormat::Png8 => "Indexed PNG (8-bit)",
+            IndexedFormat::Bmp8 => "Indexed BMP (8-bit)",
+            IndexedFormat::Png1 => "Binary PNG (1-bit)",
+            IndexedFormat::Bmp1 => "Binary BMP (1-bit)",
+        }
+    }
+
+    fn bit_depth(&self) -> u8 {
+        match self {
+            IndexedFormat::Png8 | IndexedFormat::Bmp8 => 8,
+            IndexedFormat::Png1 | IndexedFormat::Bmp1 => 1,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            IndexedFormat::Png8 | IndexedFormat::Png1 => "png",
+            IndexedFormat::Bmp8 | IndexedFormat::Bmp1 => "bmp",
+        }
+    }
+}
+
+impl Default for ExportWindow {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            format: IndexedFormat::Png8,
+            palette_source: PaletteSource::ImageLevels,
+        }
+    }
+}
+
+impl ExportWindow {
+    /// 显示导出窗口
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        anchor_levels: Option<Vec<u8>>,
+        backup_count: usize,
+    ) {
+        if self.show_window {
+            let mut show_window = self.show_window;
+            egui::Window::new("Export Indexed")
+                .open(&mut show_window)
+                .default_size([420.0, 300.0])
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.show_content(ui, temp_path, current_path, anchor_levels, backup_count);
+                });
+            self.show_window = show_window;
+        }
+    }
+
+    /// 显示窗口内容
+    fn show_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        anchor_levels: Option<Vec<u8>>,
+        backup_count: usize,
+    ) {
+        ui.label("Format:");
+        for format in [
+            IndexedFormat::Png8,
+            IndexedFormat::Bmp8,
+            IndexedFormat::Png1,
+            IndexedFormat::Bmp1,
+        ] {
+            ui.radio_value(&mut self.format, format, format.label());
+        }
+
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label("Palette:");
+            ui.radio_value(&mut self.palette_source, PaletteSource::ImageLevels, "Image levels");
+            ui.add_enabled_ui(anchor_levels.is_some(), |ui| {
+                ui.radio_value(&mut self.palette_source, PaletteSource::Anchors, "Anchor segments");
+            });
+        });
+        if anchor_levels.is_none() && self.palette_source == PaletteSource::Anchors {
+            self.palette_source = PaletteSource::ImageLevels;
+        }
+
+        // 锚点调色板预览：索引N对应第N级
+        if let (PaletteSource::Anchors, Some(levels)) = (self.palette_source, &anchor_levels) {
+            ui.horizontal_wrapped(|ui| {
+                for (i, &v) in levels.iter().enumerate() {
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(18.0, 18.0), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2.0, egui::Color32::from_gray(v));
+                    ui.label(format!("{}:{}", i, v));
+                }
+            });
+        }
+
+        ui.add_space(10.0);
+
+        if ui.button("Export...").clicked() {
+            self.export(temp_path, current_path, anchor_levels, backup_count);
+        }
+    }
+
+    /// 选择路径并导出当前处理结果
+    fn export(
+        &mut self,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        anchor_levels: Option<Vec<u8>>,
+        backup_count: usize,
+    ) {
+        let Some(temp_path) = temp_path else {
+            notifications::warn("No image loaded for export");
+            return;
+        };
+        let img = match image::open(temp_path) {
+            Ok(img) => img,
+            Err(e) => {
+                notifications::error(format!("Failed to load current image: {}", e));
+                return;
+            }
+        };
+
+        // 先确定调色板，超出容量则拒绝导出
+        let palette: Vec<[u8; 3]> = match self.palette_source {
+            PaletteSource::ImageLevels => match ImageProcessor::image_levels(&img) {
+                Ok(levels) => levels,
+                Err(e) => {
+                    notifications::error(e);
+                    return;
+                }
+            },
+            PaletteSource::Anchors => anchor_levels
+                .unwrap_or_default()
+                .into_iter()
+                .map(|v| [v, v, v])
+                .collect(),
+        };
+        let capacity = 1usize << self.format.bit_depth();
+        if palette.len() > capacity {
+            notifications::error(format!(
+                "Palette has {} levels but {} holds at most {}",
+                palette.len(),
+                self.format.label(),
+                capacity
+            ));
+            return;
+        }
+        let indices = match ImageProcessor::map_to_palette_indices(&img, &palette) {
+            Ok(indices) => indices,
+            Err(e) => {
+                notifications::error(e);
+                return;
+            }
+        };
+
+        let ext = self.format.extension();
+        let mut dialog = rfd::FileDialog::new().add_filter(ext.to_uppercase(), &[ext]);
+        if let Some(stem) = current_path.as_ref().and_then(|p| p.file_stem()).and_then(|s| s.to_str()) {
+            dialog = dialog.set_file_name(format!("{}_indexed.{}", stem, ext));
+        }
+        let Some(mut out_path) = dialog.save_file() else {
+            return;
+        };
+        if out_path.extension().is_none() {
+            out_path.set_extension(ext);
+        }
+
+        if let Err(e) = ImageProcessor::rotate_backups(&out_path, backup_count) {
+            notifications::error(format!("Failed to back up {}: {}", out_path.display(), e));
+            return;
+        }
+        let (width, height) = (img.width(), img.height());
+        let bit_depth = self.format.bit_depth();
+        let result = match self.format {
+            IndexedFormat::Png8 | IndexedFormat::Png1 => ImageProcessor::write_indexed_png(
+                &out_path, width, height, &indices, &palette, bit_depth,
+            ),
+            IndexedFormat::Bmp8 | IndexedFormat::Bmp1 => ImageProcessor::write_indexed_bmp(
+                &out_path, width, height, &indices, &palette, bit_depth,
+            ),
+        };
+        match result {
+            Ok(_) => notifications::info(format!(
+                "Exported {} levels to {}",
+                palette.len(),
+                out_path.display()
+            )),
+            Err(e) => notifications::error(format!("Failed to export: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::notifications;
+use crate::utils::{ImageProcessor, ResampleMethod};
+use image::DynamicImage;
+use std::path::PathBuf;
+
+/// Fit to loom窗口的状态
+pub struct LoomFitWindow {
+    pub show_window: bool,
+    pub unit: LengthUnit,
+    pub finished_width: f32,
+    pub finished_height: f32,
+    pub keep_aspect: bool,
+    // 经密（ends/单位长度）与纬密（picks/单位长度）
+    pub warp_density: f32,
+    pub weft_density: f32,
+    // 织机针数上限，0表示不限制
+    pub max_hooks: u32,
+    pub method: ResampleMethod,
+}
+
+/// 长度单位
+#[derive(Clone, Copy, PartialEq)]
+pub enum LengthUnit {
+    Cm,
+    Inch,
+}
+
+impl LengthUnit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LengthUnit::Cm => "cm",
+            LengthUnit::Inch => "inch",
+        }
+    }
+}
+
+impl Default for LoomFitWindow {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            unit: LengthUnit::Cm,
+            finished_width: 50.0,
+            finished_height: 50.0,
+            keep_aspect: true,
+            warp_density: 20.0,
+            weft_density: 20.0,
+            max_hooks: 0,
+            method: ResampleMethod::MajorityVote,
+        }
+    }
+}
+
+impl LoomFitWindow {
+    // 重采样结果的像素上限（原图与处理结果各一份RGBA）
+    const MAX_PIXELS: u64 = 64 * 1024 * 1024;
+
+    /// 根据成品尺寸与经纬密度计算经线数×纬线数
+    pub fn target_grid(&self) -> (u32, u32) {
+        let ends = (self.finished_width * self.warp_density).round().max(1.0) as u32;
+        let picks = (self.finished_height * self.weft_density).round().max(1.0) as u32;
+        (ends, picks)
+    }
+
+    /// 网格超出纹理边长上限或像素上限时返回原因
+    fn grid_too_large(ends: u32, picks: u32, max_side: usize) -> Option<String> {
+        if ends as usize > max_side || picks as usize > max_side {
+            Some(format!("The grid exceeds the largest displayable side of {} px", max_side))
+        } else if ends as u64 * picks as u64 > Self::MAX_PIXELS {
+            Some(format!("The grid exceeds {} megapixels", Self::MAX_PIXELS / (1024 * 1024)))
+        } else {
+            None
+        }
+    }
+
+    /// 切换单位时换算尺寸与密度，保持物理量不变
+    fn convert_unit(&mut self, to: LengthUnit) {
+        if to == self.unit {
+            return;
+        }
+        let factor = match to {
+            LengthUnit::Inch => 1.0 / 2.54,
+            LengthUnit::Cm => 2.54,
+        };
+        self.finished_width *= factor;
+        self.finished_height *= factor;
+        self.warp_density /= factor;
+        self.weft_density /= factor;
+        self.unit = to;
+    }
+
+    /// 显示Fit to loom窗口
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        original_image: &mut Option<DynamicImage>,
+        current_texture: &mut Option<egui::TextureHandle>,
+        temp_path: &Option<PathBuf>,
+        revision: &mut u64,
+    ) {
+        if self.show_window {
+            let mut show_window = self.show_window;
+            egui::Window::new("Fit to Loom")
+                .open(&mut show_window)
+                .default_size([420.0, 320.0])
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.show_content(ui, ctx, original_image, current_texture, temp_path, revision);
+                });
+            self.show_window = show_window;
+        }
+    }
+
+    /// 显示窗口内容
+    fn show_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+        original_image: &mut Option<DynamicImage>,
+        current_texture: &mut Option<egui::TextureHandle>,
+        temp_path: &Option<PathBuf>,
+        revision: &mut u64,
+    ) {
+        let image_size = original_image.as_ref().map(|img| (img.width(), img.height()));
+
+        ui.horizontal(|ui| {
+            ui.label("Unit:");
+            let mut unit = self.unit;
+            ui.radio_value(&mut unit, LengthUnit::Cm, "cm");
+            ui.radio_value(&mut unit, LengthUnit::Inch, "inch");
+            self.convert_unit(unit);
+        });
+
+        let unit = self.unit.label();
+        egui::Grid::new("loom_fit_grid").num_columns(2).show(ui, |ui| {
+            ui.label(format!("Finished width ({})", unit));
+            ui.add(egui::DragValue::new(&mut self.finished_width).speed(0.1).range(0.1..=10_000.0));
+            ui.end_row();
+
+            ui.label(format!("Finished height ({})", unit));
+            ui.add_enabled(
+                !self.keep_aspect,
+                egui::DragValue::new(&mut self.finished_height).speed(0.1).range(0.1..=10_000.0),
+            );
+            ui.end_row();
+
+            ui.label("");
+            ui.checkbox(&mut self.keep_aspect, "Keep design aspect ratio");
+            ui.end_row();
+
+            ui.label(format!("Warp density (ends/{})", unit));
+            ui.add(egui::DragValue::new(&mut self.warp_density).speed(0.1).range(0.1..=1_000.0));
+            ui.end_row();
+
+            ui.label(format!("Weft density (picks/{})", unit));
+            ui.add(egui::DragValue::new(&mut self.weft_density).speed(0.1).range(0.1..=1_000.0));
+            ui.end_row();
+
+            ui.label("Loom hooks (0 = no limit)");
+            ui.add(egui::DragValue::new(&mut self.max_hooks).range(0..=100_000));
+            ui.end_row();
+        });
+
+        // 保持成品的物理宽高比与原图一致
+        if self.keep_aspect {
+            if let Some((w, h)) = image_size {
+                self.finished_height = self.finished_width * h as f32 / w as f32;
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Resampling:");
+            ui.radio_value(&mut self.method, ResampleMethod::Nearest, "Nearest");
+            ui.radio_value(&mut self.method, ResampleMethod::AreaAverage, "Area average");
+            ui.radio_value(&mut self.method, ResampleMethod::MajorityVote, "Majority vote");
+        });
+
+        ui.add_space(10.0);
+
+        let (ends, picks) = self.target_grid();
+        ui.label(format!("Result: {} ends × {} picks", ends, picks));
+        if let Some((w, h)) = image_size {
+            ui.label(format!("Current: {} × {} px", w, h));
+        }
+        if self.warp_density != self.weft_density {
+            // 经纬密度不同时，每个像素在布面上不是正方形
+            ui.label(format!(
+                "Non-square density: each pixel is {:.3} times as tall as it is wide on the fabric",
+                self.warp_density / self.weft_density
+            ));
+        }
+        let exceeds_hooks = self.max_hooks > 0 && ends > self.max_hooks;
+        if exceeds_hooks {
+            ui.colored_label(
+                egui::Color32::from_rgb(230, 80, 80),
+                format!("{} ends exceed the loom's {} hooks", ends, self.max_hooks),
+            );
+        }
+
+        let too_large = Self::grid_too_large(ends, picks, ctx.input(|i| i.max_texture_side));
+        if let Some(reason) = &too_large {
+            ui.colored_label(
+                egui::Color32::from_rgb(230, 80, 80),
+                format!("{}; lower the size or density", reason),
+            );
+        }
+
+        ui.add_space(10.0);
+
+        if ui
+            .add_enabled(
+                image_size.is_some() && !exceeds_hooks && too_large.is_none(),
+                egui::Button::new("Fit to Loom"),
+            )
+            .clicked()
+        {
+            self.apply(ctx, original_image, current_texture, temp_path, revision);
+        }
+    }
+
+    /// 对原图和当前处理结果同时重采样，使后续处理也在织机网格上进行
+    fn apply(
+        &mut self,
+        ctx: &egui::Context,
+        original_image: &mut Option<DynamicImage>,
+        current_texture: &mut Option<egui::TextureHandle>,
+        temp_path: &Option<PathBuf>,
+        revision: &mut u64,
+    ) {
+        let Some(original_img) = original_image.as_ref() else {
+            notifications::warn("No image loaded");
+            return;
+        };
+        let (ends, picks) = self.target_grid();
+        if let Some(reason) = Self::grid_too_large(ends, picks, ctx.input(|i| i.max_texture_side)) {
+            notifications::error(reason);
+            return;
+        }
+
+        let current_img = temp_path
+            .as_ref()
+            .and_then(|p| image::open(p).ok())
+            .unwrap_or_else(|| original_img.clone());
+        let resampled = ImageProcessor::resample(&current_img, ends, picks, self.method);
+        *original_image = Some(ImageProcessor::resample(original_img, ends, picks, self.method));
+
+        *current_texture = Some(ImageProcessor::update_texture_from_image(&resampled, ctx));
+        if let Some(temp_path) = temp_path {
+            if let Err(e) = ImageProcessor::save_to_temp(&resampled, temp_path, revision) {
+                notifications::error(format!("Failed to save to temp file: {}", e));
+                return;
+            }
+        }
+        notifications::info(format!("Resampled to {} ends × {} picks", ends, picks));
+    }
+}
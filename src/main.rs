@@ -1,6 +1,25 @@
 mod main_window;
+mod notifications;
+mod anchors_metadata;
 mod color_reflection_window;
+mod commands;
+mod compare_view;
+mod edit_tools;
+mod export_window;
+mod loom_fit_window;
+mod metadata_window;
+mod pixel_grid;
+mod pixel_inspector;
+mod png_metadata;
+mod repeat_view;
+mod save_as_window;
+mod segment_overlay;
+mod selection;
+mod session;
+mod settings;
+mod stats_window;
 mod utils;
+mod view_transform;
 
 use main_window::MainWindow;
 
@@ -14,6 +33,21 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Image Viewer",
         options,
-        Box::new(|_cc| Ok(Box::new(MainWindow::default()))),
+        Box::new(|cc| {
+            let mut app = MainWindow::default();
+            if let Some(storage) = cc.storage {
+                app.restore(storage);
+            }
+            app.recoverable_sessions = session::Session::recoverable();
+            // 命令行传入的文件：打开第一个
+            let mut args = std::env::args_os().skip(1);
+            if let Some(path) = args.next() {
+                app.load_image(&cc.egui_ctx, std::path::Path::new(&path));
+            }
+            if args.next().is_some() {
+                notifications::info("Only one image can be open at a time; extra arguments were ignored");
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
\ No newline at end of file
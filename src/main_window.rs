@@ -1,20 +1,73 @@
+use crate::anchors_metadata::AnchorsMetadata;
 use crate::color_reflection_window::ColorReflectionWindow;
-use crate::utils::{GrayscaleMode, ImageProcessor, UiUtils};
-use egui;
+use crate::commands::{Command, CommandPalette, KeyBindings};
+use crate::compare_view::{CompareMode, CompareView};
+use crate::edit_tools::EditTools;
+use crate::export_window::ExportWindow;
+use crate::loom_fit_window::LoomFitWindow;
+use crate::metadata_window::MetadataWindow;
+use crate::notifications::{self, Notifications};
+use crate::pixel_grid::PixelGrid;
+use crate::pixel_inspector::PixelInspector;
+use crate::repeat_view::RepeatView;
+use crate::segment_overlay::SegmentOverlay;
+use crate::save_as_window::SaveAsWindow;
+use crate::selection::Selection;
+use crate::session::{RecoverableSession, Session};
+use crate::settings::{RecentFiles, Settings};
+use crate::stats_window::StatsWindow;
+use crate::utils::{GrayscaleMode, ImageProcessor, RepeatArrangement, UiUtils};
+use crate::view_transform::ViewTransform;
 use image::DynamicImage;
 use std::fs;
 use std::path::PathBuf;
 
+/// 会替换或关闭当前文档、需先确认未保存修改的操作
+enum PendingAction {
+    OpenDialog,
+    Open(PathBuf),
+    Paste,
+    Exit,
+}
+
 /// 主窗口的状态
 pub struct MainWindow {
     pub current_texture: Option<egui::TextureHandle>,
     pub current_path: Option<PathBuf>,
-    pub zoom_factor: f32,
-    pub pan_offset: egui::Vec2,
+    pub view: ViewTransform,
     pub original_image: Option<DynamicImage>,
     pub temp_path: Option<PathBuf>,
     pub color_reflection_window: ColorReflectionWindow,
+    pub export_window: ExportWindow,
+    pub loom_fit_window: LoomFitWindow,
+    pub stats_window: StatsWindow,
+    pub metadata_window: MetadataWindow,
+    pub repeat_view: RepeatView,
+    pub pixel_grid: PixelGrid,
+    pub compare_view: CompareView,
+    pub pixel_inspector: PixelInspector,
+    pub segment_overlay: SegmentOverlay,
+    pub edit_tools: EditTools,
+    pub selection: Selection,
     pub grayscale_mode: GrayscaleMode,
+    pub key_bindings: KeyBindings,
+    pub command_palette: CommandPalette,
+    pub recent_files: RecentFiles,
+    pub save_as_window: SaveAsWindow,
+    // 处理结果的修订号：每写入一次工作文件加一，依赖处理结果的缓存据此判断是否过期
+    working_revision: u64,
+    // 处理结果的级数与尺寸缓存：(修订号, 纹理, 级数, 尺寸)
+    status_levels: Option<(u64, egui::TextureId, usize, (u32, u32))>,
+    pub session: Option<Session>,
+    pub recoverable_sessions: Vec<RecoverableSession>,
+    pub notifications: Notifications,
+    // 最近一次保存时的处理结果修订号；未命名或恢复的文档为None
+    saved_revision: Option<u64>,
+    // 等待“保存/放弃/取消”确认的操作
+    pending_action: Option<PendingAction>,
+    // 已确认退出，不再拦截关闭请求
+    allow_close: bool,
+    window_title: String,
 }
 
 impl Default for MainWindow {
@@ -22,12 +75,38 @@ impl Default for MainWindow {
         Self {
             current_texture: None,
             current_path: None,
-            zoom_factor: 1.0,
-            pan_offset: egui::Vec2::ZERO,
+            view: ViewTransform::default(),
             original_image: None,
             temp_path: None,
             color_reflection_window: ColorReflectionWindow::default(),
+            export_window: ExportWindow::default(),
+            loom_fit_window: LoomFitWindow::default(),
+            stats_window: StatsWindow::default(),
+            metadata_window: MetadataWindow::default(),
+            repeat_view: RepeatView::default(),
+            pixel_grid: PixelGrid::default(),
+            compare_view: CompareView::default(),
+            pixel_inspector: PixelInspector::default(),
+            segment_overlay: SegmentOverlay::default(),
+            edit_tools: EditTools::default(),
+            selection: Selection::default(),
             grayscale_mode: GrayscaleMode::Default,
+            key_bindings: KeyBindings::load().unwrap_or_else(|e| {
+                notifications::error(e);
+                KeyBindings::default()
+            }),
+            command_palette: CommandPalette::default(),
+            recent_files: RecentFiles::default(),
+            save_as_window: SaveAsWindow::default(),
+            working_revision: 0,
+            status_levels: None,
+            session: None,
+            recoverable_sessions: Vec::new(),
+            notifications: Notifications::default(),
+            saved_revision: None,
+            pending_action: None,
+            allow_close: false,
+            window_title: String::new(),
         }
     }
 }
@@ -36,35 +115,334 @@ impl eframe::App for MainWindow {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         self.show(ctx, frame);
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, Settings::STORAGE_KEY, &self.settings());
+        eframe::set_value(storage, RecentFiles::STORAGE_KEY, &self.recent_files);
+    }
 }
 
 impl MainWindow {
+    /// 从eframe存储恢复上次会话的设置与最近文件
+    pub fn restore(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(settings) = eframe::get_value::<Settings>(storage, Settings::STORAGE_KEY) {
+            self.apply_settings(settings);
+        }
+        if let Some(recent_files) = eframe::get_value(storage, RecentFiles::STORAGE_KEY) {
+            self.recent_files = recent_files;
+        }
+    }
+
+    /// 当前需要持久化的设置
+    fn settings(&self) -> Settings {
+        Settings {
+            grayscale_mode: self.grayscale_mode,
+            reflection_mode: self.color_reflection_window.reflection_mode,
+            slider_values: self.color_reflection_window.slider_values.clone(),
+            isolate_segments: self.color_reflection_window.isolate_segments,
+            show_contours: self.color_reflection_window.show_contours,
+            pixel_grid: self.pixel_grid.clone(),
+            compare_mode: self.compare_view.mode,
+            compare_split: self.compare_view.split,
+            click_sets_anchor: self.pixel_inspector.click_sets_anchor,
+            save_format: self.save_as_window.format,
+            embed_anchors: self.save_as_window.embed_anchors,
+            name_template: self.save_as_window.name_template.clone(),
+            never_overwrite_source: self.save_as_window.never_overwrite_source,
+            backup_count: self.save_as_window.backup_count,
+            strip_metadata: self.save_as_window.strip_metadata,
+        }
+    }
+
+    fn apply_settings(&mut self, settings: Settings) {
+        self.grayscale_mode = settings.grayscale_mode;
+        let reflection = &mut self.color_reflection_window;
+        reflection.reflection_mode = settings.reflection_mode;
+        reflection.slider_amount = (!settings.slider_values.is_empty()).then_some(settings.slider_values.len());
+        reflection.slider_amount_input = reflection.slider_amount.map(|n| n.to_string()).unwrap_or_default();
+        reflection.slider_values = settings.slider_values;
+        reflection.isolate_segments = settings.isolate_segments;
+        reflection.show_contours = settings.show_contours;
+        self.pixel_grid = settings.pixel_grid;
+        self.compare_view.mode = settings.compare_mode;
+        self.compare_view.split = settings.compare_split.clamp(0.0, 1.0);
+        self.pixel_inspector.click_sets_anchor = settings.click_sets_anchor;
+        self.save_as_window.format = settings.save_format;
+        self.save_as_window.embed_anchors = settings.embed_anchors;
+        self.save_as_window.name_template = settings.name_template;
+        self.save_as_window.never_overwrite_source = settings.never_overwrite_source;
+        self.save_as_window.backup_count = settings.backup_count;
+        self.save_as_window.strip_metadata = settings.strip_metadata;
+    }
+
+    /// 恢复默认设置与窗口布局（保留最近文件列表）
+    fn reset_settings(&mut self, ctx: &egui::Context) {
+        self.apply_settings(Settings::default());
+        ctx.memory_mut(|memory| *memory = Default::default());
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(1200.0, 800.0)));
+        notifications::info("Settings reset to defaults");
+    }
+
     /// 显示主窗口
     pub fn show(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.edit_tools.sync(&self.current_texture, &self.temp_path);
+        self.handle_commands(ctx);
+        self.handle_dropped_files(ctx);
+        self.notifications.collect();
+        self.handle_close_request(ctx);
+        self.update_title(ctx);
         self.show_menu_bar(ctx, frame);
+        self.show_edit_toolbar(ctx);
+        self.show_selection_toolbar(ctx);
+        self.show_status_bar(ctx);
         self.show_toolbar(ctx);
         self.show_color_reflection_window(ctx);
+        self.show_export_window(ctx);
+        self.show_save_as_window(ctx);
+        self.show_loom_fit_window(ctx);
+        self.stats_window.show(
+            ctx,
+            self.working_revision,
+            &self.temp_path,
+            &self.current_path,
+            &self.loom_fit_window,
+            &self.color_reflection_window,
+        );
+        self.metadata_window.show(
+            ctx,
+            &self.current_path,
+            self.save_as_window.never_overwrite_source,
+            self.save_as_window.backup_count,
+            &mut self.save_as_window.strip_metadata,
+        );
+        self.pixel_inspector.show(
+            ctx,
+            &self.original_image,
+            self.grayscale_mode,
+            &self.color_reflection_window,
+        );
         self.show_main_display(ctx);
+        self.show_recovery_window(ctx);
+        self.show_unsaved_changes_prompt(ctx);
+        self.notifications.show_log_window(ctx);
+        let bottom_margin = ctx.content_rect().bottom() - ctx.available_rect().bottom();
+        self.notifications.show_toasts(ctx, bottom_margin);
+        self.autosave_session(ctx);
     }
 
     /// 显示菜单栏
     fn show_menu_bar(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
+                let has_image = self.current_texture.is_some();
                 ui.menu_button("File", |ui| {
-                    if ui.button("Open Image").clicked() {
-                        self.open_image_dialog(ctx);
-                        ui.close();
+                    self.menu_item(ui, ctx, Command::OpenImage, true);
+                    ui.menu_button("Open Recent", |ui| {
+                        if let Some(path) = self.recent_files.show_menu(ui, ctx) {
+                            self.request(ctx, PendingAction::Open(path));
+                        }
+                    });
+                    self.menu_item(ui, ctx, Command::PasteImage, true);
+                    self.menu_item(ui, ctx, Command::FastSave, has_image);
+                    self.menu_item(ui, ctx, Command::SaveWithAnchors, has_image);
+                    self.menu_item(ui, ctx, Command::SaveAs, has_image);
+                    ui.checkbox(
+                        &mut self.save_as_window.never_overwrite_source,
+                        "Never Overwrite Source",
+                    );
+                    self.menu_item(ui, ctx, Command::ExportIndexed, true);
+                    ui.separator();
+                    self.menu_item(ui, ctx, Command::ShowCommandPalette, true);
+                    ui.menu_button("Key Bindings", |ui| {
+                        self.menu_item(ui, ctx, Command::WriteKeyBindings, true);
+                        self.menu_item(ui, ctx, Command::ReloadKeyBindings, true);
+                    });
+                    self.menu_item(ui, ctx, Command::ResetSettings, true);
+                    ui.separator();
+                    self.menu_item(ui, ctx, Command::Exit, true);
+                });
+                ui.menu_button("Edit", |ui| {
+                    self.menu_item(ui, ctx, Command::Undo, self.edit_tools.can_undo());
+                    self.menu_item(ui, ctx, Command::Redo, self.edit_tools.can_redo());
+                    ui.separator();
+                    self.menu_item(ui, ctx, Command::CopyResult, has_image);
+                    self.menu_item(ui, ctx, Command::PasteImage, true);
+                    ui.separator();
+                    ui.checkbox(&mut self.edit_tools.show_toolbar, "Pixel Editing Tools");
+                });
+                ui.menu_button("Select", |ui| {
+                    ui.checkbox(&mut self.selection.show_toolbar, "Selection Tools");
+                    ui.separator();
+                    let has_original = self.original_image.is_some();
+                    self.menu_item(ui, ctx, Command::SelectAll, has_original);
+                    self.menu_item(ui, ctx, Command::InvertSelection, has_original);
+                    self.menu_item(ui, ctx, Command::Deselect, true);
+                    ui.separator();
+                    self.menu_item(ui, ctx, Command::SaveMask, true);
+                    self.menu_item(ui, ctx, Command::LoadMask, true);
+                });
+                ui.menu_button("View", |ui| {
+                    self.menu_item(ui, ctx, Command::ZoomIn, has_image);
+                    self.menu_item(ui, ctx, Command::ZoomOut, has_image);
+                    self.menu_item(ui, ctx, Command::FitToWindow, has_image);
+                    self.menu_item(ui, ctx, Command::FillWindow, has_image);
+                    self.menu_item(ui, ctx, Command::ActualSize, has_image);
+                    ui.separator();
+                    self.pixel_grid.show_settings(ui);
+                    ui.separator();
+                    ui.label("Compare with Original");
+                    for mode in [CompareMode::Off, CompareMode::SideBySide, CompareMode::Split] {
+                        ui.radio_value(&mut self.compare_view.mode, mode, mode.label());
                     }
-                    if ui.button("Exit").clicked() {
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        ui.close();
+                    ui.checkbox(&mut self.compare_view.show_original, "Show Original")
+                        .on_hover_text(self.key_bindings.shortcut_text(ctx, Command::ToggleOriginal));
+                    ui.label("Hold Space to flicker");
+                    ui.separator();
+                    ui.checkbox(&mut self.pixel_inspector.show_window, "Pixel Inspector");
+                    self.menu_item(ui, ctx, Command::MetadataInspector, true);
+                    self.menu_item(ui, ctx, Command::ToggleNotificationLog, true);
+                });
+                ui.menu_button("Repeat", |ui| {
+                    ui.checkbox(&mut self.repeat_view.enabled, "Repeat Preview");
+                    ui.separator();
+                    for arrangement in [
+                        RepeatArrangement::Straight,
+                        RepeatArrangement::HalfDrop,
+                        RepeatArrangement::HalfBrick,
+                        RepeatArrangement::Mirror,
+                    ] {
+                        ui.radio_value(&mut self.repeat_view.arrangement, arrangement, arrangement.label());
                     }
+                    ui.separator();
+                    ui.checkbox(&mut self.repeat_view.seam_check, "Seam Check");
+                    ui.horizontal(|ui| {
+                        ui.label("Tolerance");
+                        ui.add(egui::DragValue::new(&mut self.repeat_view.seam_tolerance));
+                    });
+                    ui.separator();
+                    self.menu_item(ui, ctx, Command::BakeRepeatUnit, has_image);
                 });
             });
         });
     }
 
+    /// 菜单项：显示命令名与快捷键，点击时执行命令
+    fn menu_item(&mut self, ui: &mut egui::Ui, ctx: &egui::Context, command: Command, enabled: bool) {
+        let button = egui::Button::new(command.label())
+            .shortcut_text(self.key_bindings.shortcut_text(ctx, command));
+        if ui.add_enabled(enabled, button).clicked() {
+            self.run_command(ctx, command);
+            ui.close();
+        }
+    }
+
+    /// 处理快捷键与命令面板
+    fn handle_commands(&mut self, ctx: &egui::Context) {
+        if let Some(command) = self.key_bindings.triggered(ctx) {
+            self.run_command(ctx, command);
+        }
+        if let Some(command) = self.command_palette.show(ctx, &self.key_bindings) {
+            self.run_command(ctx, command);
+        }
+    }
+
+    /// 执行命令
+    fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        let image_size = self.current_texture.as_ref().map(|t| t.size_vec2());
+        let original_size = self.original_image.as_ref().map(|img| (img.width(), img.height()));
+        match command {
+            Command::OpenImage => self.request(ctx, PendingAction::OpenDialog),
+            Command::PasteImage => self.request(ctx, PendingAction::Paste),
+            Command::CopyResult => self.copy_result(),
+            Command::FastSave => self.fast_save(),
+            Command::SaveWithAnchors => self.save_with_anchors(),
+            Command::SaveAs => self.save_as_window.show_window = true,
+            Command::ExportIndexed => self.export_window.show_window = true,
+            Command::Exit => self.request(ctx, PendingAction::Exit),
+            Command::Undo => self.edit_tools.undo(&mut self.current_texture, &self.temp_path, &mut self.working_revision),
+            Command::Redo => self.edit_tools.redo(&mut self.current_texture, &self.temp_path, &mut self.working_revision),
+            Command::TogglePixelTools => self.edit_tools.show_toolbar = !self.edit_tools.show_toolbar,
+            Command::ToggleSelectionTools => self.selection.show_toolbar = !self.selection.show_toolbar,
+            Command::SelectAll => {
+                if let Some((w, h)) = original_size {
+                    self.selection.select_all(w, h);
+                }
+            }
+            Command::InvertSelection => {
+                if let Some((w, h)) = original_size {
+                    self.selection.invert(w, h);
+                }
+            }
+            Command::Deselect => self.selection.clear(),
+            Command::SaveMask => self.save_mask_dialog(),
+            Command::LoadMask => self.load_mask_dialog(),
+            Command::ZoomIn => self.view.zoom_centered(ViewTransform::step_zoom(self.view.zoom, true)),
+            Command::ZoomOut => self.view.zoom_centered(ViewTransform::step_zoom(self.view.zoom, false)),
+            Command::FitToWindow => {
+                if let Some(size) = image_size {
+                    self.view.fit(size);
+                }
+            }
+            Command::FillWindow => {
+                if let Some(size) = image_size {
+                    self.view.fill(size);
+                }
+            }
+            Command::ActualSize => self.view.reset(),
+            Command::ToggleOriginal => self.compare_view.show_original = !self.compare_view.show_original,
+            Command::CompareOff => self.compare_view.mode = CompareMode::Off,
+            Command::CompareSideBySide => self.compare_view.mode = CompareMode::SideBySide,
+            Command::CompareSplit => self.compare_view.mode = CompareMode::Split,
+            Command::TogglePixelGrid => self.pixel_grid.enabled = !self.pixel_grid.enabled,
+            Command::ToggleNotificationLog => self.notifications.toggle_log(),
+            Command::TogglePixelInspector => {
+                self.pixel_inspector.show_window = !self.pixel_inspector.show_window
+            }
+            Command::RestoreOriginal => self.original(ctx),
+            Command::BlackAndWhite => self.apply_grayscale_current_mode(ctx),
+            Command::Clean => self.clean_image(ctx),
+            Command::ColorReflection => self.color_reflection_window.show_window = true,
+            Command::ApplyColorReflection => {
+                let mask = original_size.and_then(|(w, h)| self.selection.mask_for(w, h));
+                self.color_reflection_window.apply_color_reflection(
+                    ctx,
+                    &self.original_image,
+                    &mut self.current_texture,
+                    &self.temp_path,
+                    &mut self.working_revision,
+                    &self.grayscale_mode,
+                    mask,
+                );
+            }
+            Command::LoadAnchors => {
+                self.color_reflection_window.show_window = true;
+                self.color_reflection_window.load_anchors_from_png(
+                    &self.current_path,
+                    &self.temp_path,
+                    &mut self.grayscale_mode,
+                );
+            }
+            Command::FitToLoom => self.loom_fit_window.show_window = true,
+            Command::Statistics => self.stats_window.show_window = true,
+            Command::MetadataInspector => self.metadata_window.show_window = true,
+            Command::ToggleRepeatPreview => self.repeat_view.enabled = !self.repeat_view.enabled,
+            Command::BakeRepeatUnit => self.bake_repeat_unit(ctx),
+            Command::ShowCommandPalette => self.command_palette.toggle(),
+            Command::WriteKeyBindings => match self.key_bindings.write_file() {
+                Ok(path) => notifications::info(format!("Key bindings written to {}", path.display())),
+                Err(e) => notifications::error(format!("Failed to write key bindings: {}", e)),
+            },
+            Command::ReloadKeyBindings => match KeyBindings::load() {
+                Ok(bindings) => {
+                    self.key_bindings = bindings;
+                    notifications::info("Key bindings reloaded");
+                }
+                Err(e) => notifications::error(format!("Failed to load key bindings: {}", e)),
+            },
+            Command::ResetSettings => self.reset_settings(ctx),
+        }
+    }
+
     /// 显示工具栏
     fn show_toolbar(&mut self, ctx: &egui::Context) {
         egui::TopBottomPanel::bottom("toolbar").show(ctx, |ui| {
@@ -113,6 +491,12 @@ impl MainWindow {
                     if ui.button("Clean").clicked() {
                         self.clean_image(ctx);
                     }
+                    if ui.button("Fit to Loom").clicked() {
+                        self.loom_fit_window.show_window = true;
+                    }
+                    if ui.button("Statistics").clicked() {
+                        self.stats_window.show_window = true;
+                    }
                 });
 
                 // 中间缩放信息
@@ -121,7 +505,21 @@ impl MainWindow {
                     egui::Layout::left_to_right(egui::Align::Center),
                     |ui| {
                         ui.centered_and_justified(|ui| {
-                            ui.label(format!("Zoom: {:.1}%", self.zoom_factor * 100.0));
+                            let mut info = format!("Zoom: {:.1}%", self.view.zoom * 100.0);
+                            // 鼠标所在的像素坐标
+                            if let (Some(texture), Some(pos)) = (&self.current_texture, ctx.pointer_hover_pos()) {
+                                let size = texture.size_vec2();
+                                let p = self.view.screen_to_image(size, pos).floor();
+                                if self.view.viewport.contains(pos) && p.x >= 0.0 && p.y >= 0.0 && p.x < size.x && p.y < size.y {
+                                    info.push_str(&format!("  ({}, {})", p.x, p.y));
+                                }
+                            }
+                            if self.repeat_view.enabled && self.repeat_view.seam_check {
+                                if let Some(count) = self.repeat_view.seam_count() {
+                                    info.push_str(&format!("  Seam mismatches: {}", count));
+                                }
+                            }
+                            ui.label(info);
                         });
                     },
                 );
@@ -136,60 +534,140 @@ impl MainWindow {
 
     /// 显示Color Reflection窗口
     fn show_color_reflection_window(&mut self, ctx: &egui::Context) {
+        let mask = self
+            .original_image
+            .as_ref()
+            .and_then(|img| self.selection.mask_for(img.width(), img.height()));
         self.color_reflection_window.show(
             ctx,
             &self.original_image,
             &mut self.current_texture,
             &self.temp_path,
+            &mut self.working_revision,
             &self.current_path,
             &mut self.grayscale_mode,
+            mask,
         );
     }
 
+    /// 显示索引导出窗口
+    fn show_export_window(&mut self, ctx: &egui::Context) {
+        let anchor_levels = self.color_reflection_window.applied_levels();
+        self.export_window.show(
+            ctx,
+            &self.temp_path,
+            &self.current_path,
+            anchor_levels,
+            self.save_as_window.backup_count,
+        );
+    }
+
+    /// 显示Save As窗口
+    fn show_save_as_window(&mut self, ctx: &egui::Context) {
+        if !self.save_as_window.show_window {
+            return;
+        }
+        let anchors_json = self
+            .color_reflection_window
+            .build_anchors_metadata_json(&self.grayscale_mode);
+        if let Some(path) = self
+            .save_as_window
+            .show(ctx, &self.temp_path, &self.current_path, anchors_json)
+        {
+            // 之后的保存与窗口标题都指向刚写入的文件
+            self.current_path = Some(path);
+            self.mark_saved();
+        }
+    }
+
+    /// 显示Fit to loom窗口
+    fn show_loom_fit_window(&mut self, ctx: &egui::Context) {
+        let before = self.original_image.as_ref().map(|img| (img.width(), img.height()));
+        self.loom_fit_window.show(
+            ctx,
+            &mut self.original_image,
+            &mut self.current_texture,
+            &self.temp_path,
+            &mut self.working_revision,
+        );
+        // 原图被重采样后依赖原图的缓存需要重建
+        if self.original_image.as_ref().map(|img| (img.width(), img.height())) != before {
+            self.original_changed();
+        }
+    }
+
+    /// 原图被替换后丢弃依赖原图的显示缓存
+    fn original_changed(&mut self) {
+        self.compare_view.invalidate();
+        self.segment_overlay.invalidate();
+        if let Some(session) = &mut self.session {
+            session.original_changed();
+        }
+    }
+
+    /// 显示像素编辑工具栏
+    fn show_edit_toolbar(&mut self, ctx: &egui::Context) {
+        let anchor_levels = self.color_reflection_window.applied_levels();
+        self.edit_tools.show_toolbar(
+            ctx,
+            &mut self.current_texture,
+            &self.temp_path,
+            &mut self.working_revision,
+            &anchor_levels,
+        );
+    }
+
+    /// 显示选区工具栏
+    fn show_selection_toolbar(&mut self, ctx: &egui::Context) {
+        let image_size = self.original_image.as_ref().map(|img| (img.width(), img.height()));
+        self.selection.show_toolbar(ctx, image_size);
+    }
+
     /// 显示主显示区域
     fn show_main_display(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
             UiUtils::draw_checkerboard_background(ui);
 
-            // 处理缩放
-            if ui.input(|i| i.raw_scroll_delta.y != 0.0) {
-                let zoom_delta = ui.input(|i| i.raw_scroll_delta.y) * 0.01;
-                let old_zoom = self.zoom_factor;
-                self.zoom_factor = (self.zoom_factor + zoom_delta).max(0.1).min(5.0);
-
-                // 保持鼠标位置不变
-                if let Some(mouse_pos) = ui.input(|i| i.pointer.hover_pos()) {
-                    let zoom_ratio = self.zoom_factor / old_zoom;
-                    // 计算鼠标相对于图像中心的位置
-                    let image_center = egui::Pos2::new(
-                        ui.available_size().x / 2.0 + self.pan_offset.x,
-                        ui.available_size().y / 2.0 + self.pan_offset.y,
-                    );
-                    let mouse_offset = mouse_pos - image_center;
-                    let new_offset = mouse_pos - mouse_offset * zoom_ratio;
-                    self.pan_offset = egui::Vec2::new(
-                        new_offset.x - ui.available_size().x / 2.0,
-                        new_offset.y - ui.available_size().y / 2.0,
-                    );
-                }
-            }
+            // 处理缩放（以面板的实际矩形为准，鼠标位置为锚）
+            self.view.viewport = ui.max_rect();
+            self.view.handle_zoom_input(ui);
 
             if let Some(texture) = &self.current_texture {
-                let texture_size = texture.size_vec2();
-                let scaled_size = texture_size * self.zoom_factor;
+                let image_rect = self.view.image_rect(texture.size_vec2());
+                let scaled_size = image_rect.size();
 
-                let available_size = ui.available_size();
-                let image_pos = egui::Pos2::new(
-                    (available_size.x - scaled_size.x) / 2.0 + self.pan_offset.x,
-                    (available_size.y - scaled_size.y) / 2.0 + self.pan_offset.y,
-                );
+                // 循环预览：整个显示区域都可拖拽，平铺循环单元
+                if self.repeat_view.enabled {
+                    let response = ui.allocate_rect(ui.max_rect(), egui::Sense::click_and_drag());
+                    if response.dragged() {
+                        self.view.pan += ui.input(|i| i.pointer.delta());
+                    }
+                    self.repeat_view
+                        .paint(ui, texture, &self.temp_path, image_rect.min, self.view.zoom);
+                    return;
+                }
+
+                // 并排对比：左右两侧共用缩放与平移，仅用于查看
+                if self.compare_view.mode == CompareMode::SideBySide {
+                    self.compare_view.show_side_by_side(
+                        ui,
+                        texture,
+                        &self.original_image,
+                        &mut self.view,
+                    );
+                    return;
+                }
 
-                let image_rect = egui::Rect::from_min_size(image_pos, scaled_size);
                 let response = ui.allocate_rect(image_rect, egui::Sense::click_and_drag());
+                let selecting = self.selection.is_active();
+                let editing = self.edit_tools.is_active() && !selecting;
+                let inspecting = self.pixel_inspector.is_active() && !selecting && !editing;
 
-                // 处理拖拽
-                if response.dragged() {
-                    self.pan_offset += ui.input(|i| i.pointer.delta());
+                // 处理拖拽（编辑时左键用于绘制，改用中键或右键平移）
+                if response.dragged()
+                    && !((editing || selecting) && response.dragged_by(egui::PointerButton::Primary))
+                {
+                    self.view.pan += ui.input(|i| i.pointer.delta());
                 }
 
                 let image = egui::Image::new(texture)
@@ -200,6 +678,62 @@ impl MainWindow {
                         ..Default::default()
                     });
                 ui.put(image_rect, image);
+
+                // 分割对比与按键闪烁对比（只覆盖显示，不修改图像）
+                if self.compare_view.mode == CompareMode::Split {
+                    self.compare_view
+                        .paint_split(ui, image_rect, &self.original_image);
+                }
+                self.compare_view
+                    .paint_flicker(ui, image_rect, &self.original_image);
+
+                // 区段隔离与阈值等高线
+                self.segment_overlay.paint(
+                    ui,
+                    image_rect,
+                    &self.original_image,
+                    self.grayscale_mode,
+                    &self.color_reflection_window.slider_values,
+                    self.color_reflection_window.highlighted_segment(),
+                    self.color_reflection_window.contour_threshold(),
+                );
+
+                // 高倍缩放时叠加像素网格
+                self.pixel_grid
+                    .paint(ui, image_rect, texture.size(), self.view.zoom);
+
+                // 选区外区域变暗显示
+                self.selection.paint_overlay(ui, image_rect);
+
+                let image_size = self.original_image.as_ref().map(|img| (img.width(), img.height()));
+                self.pixel_inspector.track_hover(&response, image_rect, image_size);
+                if inspecting {
+                    self.pixel_inspector.handle_click(
+                        &response,
+                        &self.original_image,
+                        self.grayscale_mode,
+                        &mut self.color_reflection_window,
+                    );
+                }
+                if selecting {
+                    if let Some(size) = image_size {
+                        self.selection
+                            .handle_input(ui, &response, image_rect, &self.temp_path, size);
+                    }
+                } else if editing {
+                    let anchor_levels = self.color_reflection_window.applied_levels();
+                    let mask = image_size.and_then(|(w, h)| self.selection.mask_for(w, h));
+                    self.edit_tools.handle_input(
+                        ui,
+                        &response,
+                        image_rect,
+                        &mut self.current_texture,
+                        &self.temp_path,
+                        &mut self.working_revision,
+                        &anchor_levels,
+                        mask,
+                    );
+                }
             } else {
                 ui.centered_and_justified(|ui| {
                     ui.label("Please select an image file");
@@ -211,47 +745,416 @@ impl MainWindow {
     /// 打开图片对话框
     fn open_image_dialog(&mut self, ctx: &egui::Context) {
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("PNG images", &["png"]) 
+            .add_filter("Images", ImageProcessor::OPEN_EXTENSIONS)
+            .add_filter("All files", &["*"])
             .pick_file()
         {
-            // 进一步校验扩展名
-            if path.extension().and_then(|s| s.to_str()).map(|s| s.eq_ignore_ascii_case("png")).unwrap_or(false) {
-                self.load_image(ctx, &path);
-            } else {
-                eprintln!("Only PNG format is supported");
-            }
+            self.load_image(ctx, &path);
         }
     }
 
     /// 加载图片
-    fn load_image(&mut self, ctx: &egui::Context, path: &std::path::Path) {
-        match image::open(path) {
+    pub fn load_image(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        match ImageProcessor::open_image(path) {
             Ok(img) => {
-                // 清理上一张图片的临时文件
-                if let Some(old_temp) = self.temp_path.take() {
-                    let _ = fs::remove_file(old_temp);
+                self.recent_files.add(path, &img);
+                self.set_document(ctx, img, Some(path));
+            }
+            Err(e) => {
+                notifications::error(format!("Failed to load image {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// 以新图像替换当前文档；无路径时为未命名文档（临时文件放在系统临时目录）
+    fn set_document(&mut self, ctx: &egui::Context, img: DynamicImage, path: Option<&std::path::Path>) {
+        // 清理上一张图片的临时文件
+        if let Some(old_temp) = self.temp_path.take() {
+            let _ = fs::remove_file(old_temp);
+        }
+
+        self.original_image = Some(img.clone());
+        self.edit_tools.reset();
+        self.original_changed();
+
+        // 自动加载同名蒙版文件（<stem>.mask.png）
+        self.selection.clear();
+        if let Some(mask_path) = path.and_then(Selection::sidecar_path).filter(|p| p.exists()) {
+            if let Err(e) = self.selection.load_mask(&mask_path) {
+                notifications::error(format!("Failed to load mask: {}", e));
+            }
+        }
+
+        self.current_texture = Some(ImageProcessor::update_texture_from_image(&img, ctx));
+        self.current_path = path.map(|p| p.to_path_buf());
+        self.view.reset();
+
+        // 工作文件放在用户缓存目录的会话中（无缓存目录时退回系统临时目录）
+        if self.session.is_none() {
+            match Session::new() {
+                Ok(session) => self.session = Some(session),
+                Err(e) => notifications::error(format!("Failed to create session directory: {}", e)),
+            }
+        }
+        let temp_path = match &mut self.session {
+            Some(session) => {
+                session.original_changed();
+                session.working_path()
+            }
+            None => std::env::temp_dir().join(format!("weave_tool_egui-{}.png", std::process::id())),
+        };
+        if let Err(e) = ImageProcessor::save_to_temp(&img, &temp_path, &mut self.working_revision) {
+            notifications::error(format!("Failed to write working file {}: {}", temp_path.display(), e));
+            // 写入失败也换用新的修订号，不沿用上一张图片的缓存
+            self.working_revision += 1;
+        }
+        self.temp_path = Some(temp_path);
+        // 打开的文件视为已保存，未命名文档从一开始就有未保存内容
+        self.saved_revision = path.map(|_| self.working_revision);
+    }
+
+    /// 处理结果是否有未保存的修改
+    pub fn is_dirty(&self) -> bool {
+        self.temp_path.is_some() && self.saved_revision != Some(self.working_revision)
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved_revision = Some(self.working_revision);
+    }
+
+    /// 执行会丢弃当前处理结果的操作；有未保存修改时先询问
+    fn request(&mut self, ctx: &egui::Context, action: PendingAction) {
+        if self.is_dirty() {
+            self.pending_action = Some(action);
+        } else {
+            self.perform(ctx, action);
+        }
+    }
+
+    fn perform(&mut self, ctx: &egui::Context, action: PendingAction) {
+        match action {
+            PendingAction::OpenDialog => self.open_image_dialog(ctx),
+            PendingAction::Open(path) => self.load_image(ctx, &path),
+            PendingAction::Paste => self.paste_image(ctx),
+            PendingAction::Exit => {
+                self.allow_close = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    /// 拦截窗口关闭请求（标题栏关闭按钮、系统快捷键），有未保存修改时改为询问
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && !self.allow_close && self.is_dirty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending_action = Some(PendingAction::Exit);
+        }
+    }
+
+    /// 标题显示文件名，未保存时加 * 号
+    fn update_title(&mut self, ctx: &egui::Context) {
+        let title = if self.temp_path.is_none() {
+            "Image Viewer".to_string()
+        } else {
+            let name = self
+                .current_path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "Untitled".to_string());
+            format!("{}{} - Image Viewer", name, if self.is_dirty() { "*" } else { "" })
+        };
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+
+    /// 保存 / 放弃 / 取消 确认框
+    fn show_unsaved_changes_prompt(&mut self, ctx: &egui::Context) {
+        let Some(action) = &self.pending_action else {
+            return;
+        };
+        let name = self
+            .current_path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".to_string());
+        let question = match action {
+            PendingAction::Exit => "before exiting",
+            _ => "before opening another image",
+        };
+        let mut choice = None;
+        let response = egui::Modal::new(egui::Id::new("unsaved_changes")).show(ctx, |ui| {
+            ui.heading("Unsaved Changes");
+            ui.label(format!("Save changes to {} {}?", name, question));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    choice = Some(true);
                 }
+                if ui.button("Discard").clicked() {
+                    choice = Some(false);
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        if response.should_close() && choice.is_none() {
+            self.pending_action = None;
+            return;
+        }
+        if let Some(save) = choice {
+            if save {
+                self.fast_save();
+                // 保存失败或取消选择路径时保留当前文档
+                if self.is_dirty() {
+                    self.pending_action = None;
+                    return;
+                }
+            }
+            if let Some(action) = self.pending_action.take() {
+                self.perform(ctx, action);
+            }
+        }
+    }
 
-                self.original_image = Some(img.clone());
+    /// 状态栏：文件路径、尺寸、级数、缩放、保存状态与最近一条通知
+    fn show_status_bar(&mut self, ctx: &egui::Context) {
+        let levels = self.current_levels().map(|(levels, _)| levels);
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                match (&self.current_path, &self.temp_path) {
+                    (Some(path), _) => {
+                        ui.label(path.display().to_string());
+                    }
+                    (None, Some(_)) => {
+                        ui.label("Untitled");
+                    }
+                    (None, None) => {
+                        ui.label("No image");
+                    }
+                }
+                if let Some(img) = &self.original_image {
+                    ui.separator();
+                    ui.label(format!("{} × {} px", img.width(), img.height()));
+                }
+                if let Some(levels) = levels {
+                    ui.separator();
+                    ui.label(format!("{} levels", levels));
+                }
+                if self.current_texture.is_some() {
+                    ui.separator();
+                    ui.label(format!("{:.0}%", self.view.zoom * 100.0));
+                    ui.separator();
+                    if self.is_dirty() {
+                        ui.colored_label(egui::Color32::from_rgb(240, 190, 80), "● Modified");
+                    } else {
+                        ui.label("Saved");
+                    }
+                }
 
-                self.current_texture = Some(ImageProcessor::update_texture_from_image(&img, ctx));
-                self.current_path = Some(path.to_path_buf());
-                self.zoom_factor = 1.0;
-                self.pan_offset = egui::Vec2::ZERO;
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let errors = self.notifications.unread_errors();
+                    let label = if errors > 0 { format!("Log ({} errors)", errors) } else { "Log".to_string() };
+                    if ui.selectable_label(self.notifications.show_log, label).clicked() {
+                        self.notifications.toggle_log();
+                    }
+                    if let Some(latest) = self.notifications.latest() {
+                        ui.add(egui::Label::new(&latest.text).truncate());
+                    }
+                });
+            });
+        });
+    }
 
-                // 创建临时文件
-                if let Some(original_path) = &self.current_path {
-                    if let Some(stem) = original_path.file_stem().and_then(|s| s.to_str()) {
-                        let mut temp_path = original_path.clone();
-                        temp_path.set_file_name(format!("{}.egui_tmp.png", stem));
-                        let _ = img.save(&temp_path);
-                        self.temp_path = Some(temp_path);
+    /// 当前处理结果的级数与尺寸（按修订号与纹理缓存）
+    fn current_levels(&mut self) -> Option<(usize, (u32, u32))> {
+        let texture_id = self.current_texture.as_ref()?.id();
+        let revision = self.working_revision;
+        match self.status_levels {
+            Some((r, id, levels, size)) if r == revision && id == texture_id => Some((levels, size)),
+            _ => {
+                let img = image::open(self.temp_path.as_ref()?).ok()?;
+                let levels = ImageProcessor::count_levels(&img);
+                let size = (img.width(), img.height());
+                self.status_levels = Some((revision, texture_id, levels, size));
+                Some((levels, size))
+            }
+        }
+    }
+
+    /// 定期自动保存会话，空闲时也按时唤醒
+    fn autosave_session(&mut self, ctx: &egui::Context) {
+        let unsaved = self.is_dirty();
+        if let Some(session) = &mut self.session {
+            session.autosave(
+                self.current_path.as_deref(),
+                self.original_image.as_ref(),
+                &self.color_reflection_window,
+                unsaved,
+            );
+            ctx.request_repaint_after(Session::AUTOSAVE_INTERVAL);
+        }
+    }
+
+    /// 启动时提示恢复上次异常退出留下的会话
+    fn show_recovery_window(&mut self, ctx: &egui::Context) {
+        if self.recoverable_sessions.is_empty() {
+            return;
+        }
+        let mut recover = None;
+        let mut discard = None;
+        let mut discard_all = false;
+        egui::Window::new("Recover Unsaved Work")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("The application did not shut down cleanly. These sessions can be recovered:");
+                ui.add_space(6.0);
+                egui::Grid::new("recovery_grid").striped(true).show(ui, |ui| {
+                    for (i, session) in self.recoverable_sessions.iter().enumerate() {
+                        let label = ui.label(session.display_name());
+                        if let Some(source) = &session.source {
+                            label.on_hover_text(source.display().to_string());
+                        }
+                        let minutes = session.updated.elapsed().unwrap_or_default().as_secs() / 60;
+                        ui.label(format!("{} min ago", minutes));
+                        if ui.button("Recover").clicked() {
+                            recover = Some(i);
+                        }
+                        if ui.button("Discard").clicked() {
+                            discard = Some(i);
+                        }
+                        ui.end_row();
                     }
+                });
+                ui.add_space(6.0);
+                if ui.button("Discard All").clicked() {
+                    discard_all = true;
+                }
+            });
+
+        if discard_all {
+            for session in self.recoverable_sessions.drain(..) {
+                session.discard();
+            }
+        } else if let Some(i) = discard {
+            self.recoverable_sessions.remove(i).discard();
+        } else if let Some(i) = recover {
+            let session = self.recoverable_sessions.remove(i);
+            self.recover_session(ctx, &session);
+        }
+    }
+
+    /// 载入会话的处理结果、原图与锚点，之后删除旧会话目录
+    fn recover_session(&mut self, ctx: &egui::Context, session: &RecoverableSession) {
+        match session.load_images() {
+            Ok((working, original)) => {
+                self.set_document(ctx, working, session.source.as_deref());
+                self.saved_revision = None;
+                if let Some(original) = original {
+                    self.original_image = Some(original);
+                    self.original_changed();
+                }
+                let reflection = &mut self.color_reflection_window;
+                if !session.slider_values.is_empty() {
+                    reflection.slider_amount = Some(session.slider_values.len());
+                    reflection.slider_amount_input = session.slider_values.len().to_string();
+                    reflection.slider_values = session.slider_values.clone();
+                }
+                if let Some(mode) = session.reflection_mode {
+                    reflection.reflection_mode = mode;
                 }
+                session.discard();
+                notifications::info(format!("Recovered session: {}", session.display_name()));
+            }
+            Err(e) => {
+                notifications::error(format!("Failed to recover session: {}", e));
+            }
+        }
+    }
+
+    /// 处理拖放到窗口上的文件（只打开第一个）
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<_> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if let Some(path) = dropped.first() {
+            if dropped.len() > 1 {
+                notifications::info(format!("Multiple files dropped, opening the first: {}", path.display()));
             }
+            self.request(ctx, PendingAction::Open(path.clone()));
+        }
+
+        // 拖动经过窗口时的提示
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let screen = ctx.content_rect();
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_overlay")));
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop image to open",
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
+    /// 将剪贴板中的图像粘贴为新的未命名文档（剪贴板为文件路径时打开该文件）
+    fn paste_image(&mut self, ctx: &egui::Context) {
+        let mut clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => clipboard,
             Err(e) => {
-                eprintln!("Failed to load image: {}", e);
+                notifications::error(format!("Clipboard unavailable: {}", e));
+                return;
+            }
+        };
+        if let Ok(data) = clipboard.get_image() {
+            match image::RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned()) {
+                Some(rgba) => {
+                    notifications::info(format!("Pasted image from clipboard: {}x{}", rgba.width(), rgba.height()));
+                    self.set_document(ctx, DynamicImage::ImageRgba8(rgba), None);
+                }
+                None => notifications::error("Clipboard image data is invalid"),
             }
+            return;
+        }
+        let path = clipboard
+            .get_text()
+            .ok()
+            .map(|text| std::path::PathBuf::from(text.trim().trim_matches('"')))
+            .filter(|p| p.is_file());
+        match path {
+            Some(path) => self.load_image(ctx, &path),
+            None => notifications::warn("The clipboard does not contain an image"),
+        }
+    }
+
+    /// 将处理结果复制到剪贴板
+    fn copy_result(&mut self) {
+        let Some(temp_path) = &self.temp_path else {
+            notifications::warn("No image loaded to copy");
+            return;
+        };
+        let result = image::open(temp_path)
+            .map_err(|e| e.to_string())
+            .and_then(|img| {
+                let rgba = img.to_rgba8();
+                let data = arboard::ImageData {
+                    width: rgba.width() as usize,
+                    height: rgba.height() as usize,
+                    bytes: std::borrow::Cow::Owned(rgba.into_raw()),
+                };
+                arboard::Clipboard::new()
+                    .and_then(|mut clipboard| clipboard.set_image(data))
+                    .map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(_) => notifications::info("Result copied to clipboard"),
+            Err(e) => notifications::error(format!("Failed to copy image: {}", e)),
         }
     }
 
@@ -265,13 +1168,15 @@ impl MainWindow {
                 GrayscaleMode::Max => ImageProcessor::convert_to_grayscale_max(original_img),
                 GrayscaleMode::Min => ImageProcessor::convert_to_grayscale_min(original_img),
             };
+            let mask = self.selection.mask_for(original_img.width(), original_img.height());
+            let processed_img = ImageProcessor::limit_to_mask(processed_img, mask, &self.temp_path);
 
             self.current_texture = Some(ImageProcessor::update_texture_from_image(
                 &processed_img,
                 ctx,
             ));
             if let Some(temp_path) = &self.temp_path {
-                let _ = ImageProcessor::save_to_temp(&processed_img, temp_path);
+                let _ = ImageProcessor::save_to_temp(&processed_img, temp_path, &mut self.working_revision);
             }
         }
     }
@@ -284,70 +1189,275 @@ impl MainWindow {
                 ctx,
             ));
             if let Some(temp_path) = &self.temp_path {
-                let _ = ImageProcessor::save_to_temp(&original_img, temp_path);
+                let _ = ImageProcessor::save_to_temp(&original_img, temp_path, &mut self.working_revision);
+            }
+        }
+    }
+
+    /// 未命名文档保存前先选择文件路径；取消时返回false
+    fn pick_path_for_untitled(&mut self) -> bool {
+        if self.current_path.is_some() || self.temp_path.is_none() {
+            return true;
+        }
+        let path = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .add_filter("Images", ImageProcessor::OPEN_EXTENSIONS)
+            .set_file_name("untitled.png")
+            .save_file();
+        match path {
+            Some(path) => {
+                self.current_path = Some(path);
+                true
             }
+            None => false,
         }
     }
 
-    /// 快速保存
-    fn fast_save(&self) {
-        if let (Some(current_path), Some(temp_path)) = (&self.current_path, &self.temp_path) {
-            match fs::copy(temp_path, current_path) {
-                Ok(_) => {
-                    println!("Image saved successfully to: {}", current_path.display());
+    /// 快速保存（按原文件的格式写回）
+    fn fast_save(&mut self) {
+        if !self.pick_path_for_untitled() {
+            return;
+        }
+        let working = self.current_levels();
+        if let (Some(source_path), Some(temp_path)) = (&self.current_path, &self.temp_path) {
+            // 有损格式会改变分级后的像素且无法保存锚点，改存为同名PNG
+            let lossy_source = ImageProcessor::is_lossy(source_path);
+            // 保护源文件时按命名模板写到源文件旁边，格式与源文件相同
+            let current_path = if self.save_as_window.never_overwrite_source {
+                let ext = match source_path.extension().and_then(|s| s.to_str()) {
+                    Some(ext) if !lossy_source => ext,
+                    _ => "png",
+                };
+                match working.and_then(|(levels, size)| self.save_as_window.path_next_to_source(source_path, levels, size, ext)) {
+                    Some(path) => path,
+                    None => {
+                        notifications::error("Failed to build the output file name");
+                        return;
+                    }
+                }
+            } else if lossy_source {
+                source_path.with_extension("png")
+            } else {
+                source_path.clone()
+            };
+            let current_path = &current_path;
+            if lossy_source {
+                notifications::warn(format!(
+                    "JPEG is lossy and cannot hold anchors metadata; saving as PNG instead: {}",
+                    current_path.display()
+                ));
+            } else if !ImageProcessor::is_png(current_path)
+                && self.color_reflection_window.has_applied_reflection
+            {
+                notifications::warn(format!(
+                    "{} cannot hold anchors metadata; use Save With Anchors to keep them in a PNG",
+                    current_path.display()
+                ));
+            }
+            if let Err(e) = ImageProcessor::rotate_backups(current_path, self.save_as_window.backup_count) {
+                notifications::error(format!("Failed to back up {}: {}", current_path.display(), e));
+                return;
+            }
+            // 目标为PNG时沿用源文件的附属块（pHYs、iCCP、文本等）与已有的锚点
+            let result = if ImageProcessor::is_png(current_path) {
+                let existing_json = ImageProcessor::read_png_text_value_from_path(source_path, "anchors")
+                    .ok()
+                    .flatten();
+                ImageProcessor::write_png_with_text_from_path(
+                    temp_path,
+                    current_path,
+                    Some(source_path.as_path()),
+                    "anchors",
+                    existing_json.as_deref(),
+                )
+            } else {
+                image::open(temp_path)
+                    .map_err(|e| e.into())
+                    .and_then(|img| ImageProcessor::save_in_format(&img, current_path))
+                    .map(|_| None)
+            };
+            match result {
+                Ok(dropped) => {
+                    if let Some(reason) = dropped {
+                        notifications::warn(reason);
+                    }
+                    notifications::info(format!("Image saved successfully to: {}", current_path.display()));
+                    self.mark_saved();
                 }
                 Err(e) => {
-                    eprintln!("Failed to save image: {}", e);
+                    notifications::error(format!("Failed to save image: {}", e));
                 }
             }
         } else {
-            eprintln!("No image loaded or temp file not available for saving");
+            notifications::warn("No image loaded or temp file not available for saving");
         }
     }
 
     /// 保存并将颜色映射的锚点写入PNG文本元数据
-    fn save_with_anchors(&self) {
-        if let (Some(current_path), Some(temp_path)) = (&self.current_path, &self.temp_path) {
+    fn save_with_anchors(&mut self) {
+        if !self.pick_path_for_untitled() {
+            return;
+        }
+        let working = self.current_levels();
+        if let (Some(source_path), Some(temp_path)) = (self.current_path.clone(), self.temp_path.clone()) {
+            // 保护源文件时按命名模板在源文件旁写PNG；
+            // 否则其他格式无法保存锚点，改为另选PNG路径保存（原文件不变）
+            let current_path = if self.save_as_window.never_overwrite_source {
+                match working.and_then(|(levels, size)| self.save_as_window.path_next_to_source(&source_path, levels, size, "png")) {
+                    Some(path) => path,
+                    None => {
+                        notifications::error("Failed to build the output file name");
+                        return;
+                    }
+                }
+            } else if ImageProcessor::is_png(&source_path) {
+                source_path.clone()
+            } else {
+                let format = source_path
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("this")
+                    .to_uppercase();
+                let mut dialog = rfd::FileDialog::new()
+                    .set_title(format!("{} files cannot hold anchors metadata - save as PNG", format))
+                    .add_filter("PNG", &["png"]);
+                if let Some(dir) = source_path.parent() {
+                    dialog = dialog.set_directory(dir);
+                }
+                if let Some(stem) = source_path.file_stem().and_then(|s| s.to_str()) {
+                    dialog = dialog.set_file_name(format!("{}.png", stem));
+                }
+                let Some(mut png_path) = dialog.save_file() else {
+                    notifications::error(format!(
+                        "{} files cannot hold anchors metadata. Nothing was saved; choose a PNG file to keep the anchors.",
+                        format
+                    ));
+                    return;
+                };
+                png_path.set_extension("png");
+                png_path
+            };
+            let current_path = &current_path;
             if let Some(json) = self
                 .color_reflection_window
                 .build_anchors_metadata_json(&self.grayscale_mode)
             {
+                if let Err(e) = ImageProcessor::rotate_backups(current_path, self.save_as_window.backup_count) {
+                    notifications::error(format!("Failed to back up {}: {}", current_path.display(), e));
+                    return;
+                }
                 match crate::utils::ImageProcessor::write_png_with_text_from_path(
                     temp_path.as_path(),
                     current_path.as_path(),
+                    Some(source_path.as_path()),
                     "anchors",
-                    &json,
+                    Some(&json),
                 ) {
-                    Ok(_) => {
-                        println!(
+                    Ok(dropped) => {
+                        if let Some(reason) = dropped {
+                            notifications::warn(reason);
+                        }
+                        notifications::info(format!(
                             "Image with anchors metadata saved to: {}",
                             current_path.display()
-                        );
+                        ));
+                        self.mark_saved();
                         // 验证：立即读取并打印元数据，证明写入成功
-                        if let Ok(Some(verified)) = crate::utils::ImageProcessor::read_png_text_value_from_path(
+                        let verified = crate::utils::ImageProcessor::read_png_text_value_from_path(
                             current_path.as_path(),
                             "anchors",
-                        ) {
-                            println!("Metadata verified: {}", verified);
-                        } else {
-                            println!("Warning: Could not verify metadata after save");
+                        );
+                        match verified.ok().flatten().map(|json| AnchorsMetadata::parse(&json)) {
+                            Some(Ok(metadata)) => notifications::info(format!(
+                                "Metadata verified (v{}, {} anchors)",
+                                metadata.version,
+                                metadata.anchors.len()
+                            )),
+                            _ => notifications::warn("Could not verify metadata after save"),
                         }
                     }
-                    Err(e) => eprintln!("Failed to save image with anchors metadata: {}", e),
+                    Err(e) => notifications::error(format!("Failed to save image with anchors metadata: {}", e)),
                 }
 
                 // 同步更新临时文件也带有元数据，便于会话内读取
                 let _ = crate::utils::ImageProcessor::write_png_with_text_from_path(
                     temp_path.as_path(),
                     temp_path.as_path(),
+                    Some(temp_path.as_path()),
                     "anchors",
-                    &json,
+                    Some(&json),
                 );
             } else {
-                eprintln!("No color reflection anchors available to write into metadata");
+                notifications::warn("No color reflection anchors available to write into metadata");
             }
         } else {
-            eprintln!("No image loaded or temp file not available for saving with anchors");
+            notifications::warn("No image loaded or temp file not available for saving with anchors");
+        }
+    }
+
+    /// 将当前排列方式烘焙为新的循环单元图像（原图同步处理）
+    fn bake_repeat_unit(&mut self, ctx: &egui::Context) {
+        let arrangement = self.repeat_view.arrangement;
+        if let Some(temp_path) = self.temp_path.clone() {
+            match image::open(&temp_path) {
+                Ok(current_img) => {
+                    let unit = ImageProcessor::build_repeat_unit(&current_img, arrangement);
+                    if let Some(original_img) = &self.original_image {
+                        self.original_image =
+                            Some(ImageProcessor::build_repeat_unit(original_img, arrangement));
+                        self.original_changed();
+                    }
+                    self.current_texture =
+                        Some(ImageProcessor::update_texture_from_image(&unit, ctx));
+                    if let Err(e) = ImageProcessor::save_to_temp(&unit, &temp_path, &mut self.working_revision) {
+                        notifications::error(format!("Failed to save to temp file: {}", e));
+                        return;
+                    }
+                    // 烘焙后的单元已包含排列，预览回到直接平铺
+                    self.repeat_view.arrangement = RepeatArrangement::Straight;
+                    notifications::info(format!("Repeat unit baked: {}x{}", unit.width(), unit.height()));
+                }
+                Err(e) => {
+                    notifications::error(format!("Failed to load current image from temp file: {}", e));
+                }
+            }
+        } else {
+            notifications::warn("No image loaded for baking a repeat unit");
+        }
+    }
+
+    /// 选择路径保存当前蒙版（默认为图像旁的 <stem>.mask.png）
+    fn save_mask_dialog(&mut self) {
+        if self.selection.mask.is_none() {
+            notifications::warn("No selection to save");
+            return;
+        }
+        let mut dialog = rfd::FileDialog::new().add_filter("PNG images", &["png"]);
+        if let Some(sidecar) = self.current_path.as_deref().and_then(Selection::sidecar_path) {
+            if let Some(dir) = sidecar.parent() {
+                dialog = dialog.set_directory(dir);
+            }
+            if let Some(name) = sidecar.file_name().and_then(|s| s.to_str()) {
+                dialog = dialog.set_file_name(name);
+            }
+        }
+        if let Some(path) = dialog.save_file() {
+            match self.selection.save_mask(&path) {
+                Ok(_) => notifications::info(format!("Mask saved to: {}", path.display())),
+                Err(e) => notifications::error(format!("Failed to save mask: {}", e)),
+            }
+        }
+    }
+
+    /// 选择PNG文件加载蒙版
+    fn load_mask_dialog(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG images", &["png"])
+            .pick_file()
+        {
+            if let Err(e) = self.selection.load_mask(&path) {
+                notifications::error(format!("Failed to load mask: {}", e));
+            }
         }
     }
 
@@ -359,20 +1469,25 @@ impl MainWindow {
                 match image::open(temp_path) {
                     Ok(current_img) => {
                         let cleaned_img = ImageProcessor::clean_image(&current_img);
+                        let mask = self.selection.mask_for(current_img.width(), current_img.height());
+                        let cleaned_img = match mask {
+                            Some(mask) => ImageProcessor::composite_masked(&current_img, &cleaned_img, mask),
+                            None => cleaned_img,
+                        };
                         self.current_texture =
                             Some(ImageProcessor::update_texture_from_image(&cleaned_img, ctx));
-                        let _ = ImageProcessor::save_to_temp(&cleaned_img, temp_path);
-                        println!("Image cleaned successfully");
+                        let _ = ImageProcessor::save_to_temp(&cleaned_img, temp_path, &mut self.working_revision);
+                        notifications::info("Image cleaned successfully");
                     }
                     Err(e) => {
-                        eprintln!("Failed to load current image from temp file: {}", e);
+                        notifications::error(format!("Failed to load current image from temp file: {}", e));
                     }
                 }
             } else {
-                eprintln!("No temp file available for cleaning");
+                notifications::warn("No temp file available for cleaning");
             }
         } else {
-            eprintln!("No image loaded for cleaning");
+            notifications::warn("No image loaded for cleaning");
         }
     }
 }
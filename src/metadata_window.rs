@@ -0,0 +1,341 @@
+use crate::notifications;
+use crate::png_metadata::{self, Chunk, TextEntry};
+use crate::utils::ImageProcessor;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// PNG元数据查看与编辑窗口的状态
+#[derive(Default)]
+pub struct MetadataWindow {
+    pub show_window: bool,
+    // 已读取的文件及其修改时间，文件在磁盘上变化后重新读取
+    loaded: Option<(PathBuf, Option<SystemTime>)>,
+    // 上次检查修改时间的时刻（不必每帧访问文件系统）
+    checked: Option<Instant>,
+    // 每块的类型、长度与内容说明（读取时生成，避免每帧解压文本）
+    rows: Vec<(String, u32, String)>,
+    corrupt_text: usize,
+    error: Option<String>,
+    // 正在编辑的文本条目
+    entries: Vec<TextEntry>,
+    modified: bool,
+}
+
+impl MetadataWindow {
+    // 同一文件重新检查修改时间的间隔
+    const RECHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+    /// 显示元数据窗口
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        current_path: &Option<PathBuf>,
+        never_overwrite_source: bool,
+        backup_count: usize,
+        strip_on_export: &mut bool,
+    ) {
+        if self.show_window {
+            self.refresh(current_path.as_deref());
+            let mut show_window = self.show_window;
+            egui::Window::new("PNG Metadata")
+                .open(&mut show_window)
+                .default_size([640.0, 480.0])
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.show_content(ui, current_path.as_deref(), never_overwrite_source, backup_count, strip_on_export);
+                });
+            self.show_window = show_window;
+        }
+    }
+
+    /// 打开的文件或其修改时间变化时重新读取（有未保存的编辑时保留编辑内容）；
+    /// 同一文件的修改时间按间隔检查
+    fn refresh(&mut self, path: Option<&Path>) {
+        let path = path.filter(|p| ImageProcessor::is_png(p));
+        let same_path = path == self.loaded.as_ref().map(|(p, _)| p.as_path());
+        if same_path && self.checked.is_some_and(|t| t.elapsed() < Self::RECHECK_INTERVAL) {
+            return;
+        }
+        self.checked = Some(Instant::now());
+        let modified_time = path.and_then(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
+        let current = path.map(|p| (p.to_path_buf(), modified_time));
+        if current == self.loaded {
+            return;
+        }
+        let same_file = current.as_ref().map(|(p, _)| p) == self.loaded.as_ref().map(|(p, _)| p);
+        if same_file && self.modified {
+            return;
+        }
+        self.loaded = current;
+        self.reload();
+    }
+
+    fn reload(&mut self) {
+        self.rows.clear();
+        self.corrupt_text = 0;
+        self.entries.clear();
+        self.error = None;
+        self.modified = false;
+        let Some((path, _)) = &self.loaded else {
+            return;
+        };
+        match png_metadata::read_chunks_from_path(path) {
+            Ok(chunks) => {
+                (self.entries, self.corrupt_text) = png_metadata::decode_text_entries(&chunks);
+                for chunk in &chunks {
+                    self.rows.push((chunk.kind_name(), chunk.length, Self::describe(chunk)));
+                }
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// 显示窗口内容
+    fn show_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        current_path: Option<&Path>,
+        never_overwrite_source: bool,
+        backup_count: usize,
+        strip_on_export: &mut bool,
+    ) {
+        ui.checkbox(strip_on_export, "Strip all metadata on export")
+            .on_hover_text("Save As writes pixels only, without anchors, resolution, colour profile or text");
+        ui.separator();
+
+        let Some((path, _)) = self.loaded.clone() else {
+            if current_path.is_some() {
+                ui.label("Metadata inspection is available for PNG files only");
+            } else {
+                ui.label("No file loaded");
+            }
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(path.display().to_string());
+            if ui.button("Reload").clicked() {
+                self.loaded = None;
+                self.refresh(Some(&path));
+            }
+            if ui
+                .add_enabled(self.error.is_none(), egui::Button::new("Export Without Metadata..."))
+                .on_hover_text("Save a copy of this file with only the image data")
+                .clicked()
+            {
+                Self::export_stripped(&path);
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::from_rgb(240, 90, 90), format!("Cannot read this file: {}", error));
+            return;
+        }
+
+        ui.label(format!("Chunks ({})", self.rows.len()));
+        egui::ScrollArea::vertical()
+            .id_salt("metadata_chunks")
+            .max_height(220.0)
+            .show(ui, |ui| {
+                egui::Grid::new("metadata_chunks_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Type");
+                        ui.strong("Size");
+                        ui.strong("Contents");
+                        ui.end_row();
+                        for (kind, length, description) in &self.rows {
+                            ui.monospace(kind);
+                            ui.label(format!("{} B", length));
+                            ui.label(description);
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        ui.separator();
+        ui.label("Text entries");
+        if self.corrupt_text > 0 {
+            ui.colored_label(
+                egui::Color32::from_rgb(240, 190, 80),
+                format!("{} corrupt text entries are not shown and will be dropped on save", self.corrupt_text),
+            );
+        }
+        let mut remove: Option<usize> = None;
+        egui::ScrollArea::vertical()
+            .id_salt("metadata_entries")
+            .max_height(220.0)
+            .show(ui, |ui| {
+                for (i, entry) in self.entries.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        let keyword_changed = ui
+                            .add(egui::TextEdit::singleline(&mut entry.keyword).desired_width(120.0).hint_text("Keyword"))
+                            .changed();
+                        let text_changed = ui
+                            .add(egui::TextEdit::multiline(&mut entry.text).desired_rows(1).desired_width(360.0))
+                            .changed();
+                        if keyword_changed || text_changed {
+                            self.modified = true;
+                        }
+                        if ui.button("Delete").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+            });
+        if let Some(i) = remove {
+            self.entries.remove(i);
+            self.modified = true;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add Entry").clicked() {
+                self.entries.push(TextEntry::new("", ""));
+                self.modified = true;
+            }
+            // 文本条目只能写回打开的文件本身，开启源文件保护时不允许
+            if ui
+                .add_enabled(self.modified && !never_overwrite_source, egui::Button::new("Save to File"))
+                .on_disabled_hover_text(if never_overwrite_source {
+                    "Saving rewrites the source file; turn off File > \"Never Overwrite Source\" first"
+                } else {
+                    "No changes to save"
+                })
+                .clicked()
+            {
+                self.save_entries(&path, never_overwrite_source, backup_count);
+            }
+            if ui.add_enabled(self.modified, egui::Button::new("Revert")).clicked() {
+                self.reload();
+            }
+        });
+    }
+
+    /// 用编辑后的文本条目重写文件（图像数据与其他块原样保留）
+    fn save_entries(&mut self, path: &Path, never_overwrite_source: bool, backup_count: usize) {
+        if never_overwrite_source {
+            notifications::error(
+                "Refusing to overwrite the source file. Turn off File > \"Never Overwrite Source\" to edit its metadata.",
+            );
+            return;
+        }
+        let result = png_metadata::read_chunks_with_image_data(path)
+            .map_err(|e| e.to_string())
+            .and_then(|chunks| png_metadata::replace_text_entries(&chunks, &self.entries));
+        let chunks = match result {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                notifications::error(format!("Failed to update metadata: {}", e));
+                return;
+            }
+        };
+        if let Err(e) = ImageProcessor::rotate_backups(path, backup_count) {
+            notifications::error(format!("Failed to back up {}: {}", path.display(), e));
+            return;
+        }
+        match ImageProcessor::write_atomic(path, |w| Ok(png_metadata::write_chunks(w, &chunks)?)) {
+            Ok(_) => {
+                notifications::info(format!("Metadata saved to {}", path.display()));
+                self.loaded = None;
+                self.refresh(Some(path));
+            }
+            Err(e) => notifications::error(format!("Failed to update metadata: {}", e)),
+        }
+    }
+
+    /// 另存一份只含图像数据的副本
+    fn export_stripped(path: &Path) {
+        let mut dialog = rfd::FileDialog::new().add_filter("PNG", &["png"]);
+        if let Some(dir) = path.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            dialog = dialog.set_file_name(format!("{}_stripped.png", stem));
+        }
+        let Some(out_path) = dialog.save_file() else {
+            return;
+        };
+        if ImageProcessor::same_file(&out_path, path) {
+            notifications::error("Choose a different file; the stripped copy cannot replace its source");
+            return;
+        }
+        let chunks = png_metadata::read_chunks_with_image_data(path);
+        let result = chunks.map_err(|e| e.into()).and_then(|chunks| {
+            let stripped = png_metadata::strip_metadata(&chunks);
+            ImageProcessor::write_atomic(&out_path, |w| Ok(png_metadata::write_chunks(w, &stripped)?))
+        });
+        match result {
+            Ok(_) => notifications::info(format!("Copy without metadata saved to {}", out_path.display())),
+            Err(e) => notifications::error(format!("Failed to export {}: {}", out_path.display(), e)),
+        }
+    }
+
+    /// 块内容的简要说明
+    fn describe(chunk: &Chunk) -> String {
+        let d = &chunk.data;
+        let be32 = |i: usize| d.get(i..i + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        if let Some(entry) = chunk.decode_text() {
+            return match entry {
+                Ok(entry) => {
+                    let preview: String = entry.text.chars().take(80).collect();
+                    let ellipsis = if entry.text.chars().count() > 80 { "…" } else { "" };
+                    format!("{}: {}{}", entry.keyword, preview.replace('\n', " "), ellipsis)
+                }
+                Err(e) => format!("corrupt text: {}", e),
+            };
+        }
+        match &chunk.kind {
+            b"IHDR" if d.len() >= 13 => {
+                let color = match d[9] {
+                    0 => "grayscale",
+                    2 => "RGB",
+                    3 => "indexed",
+                    4 => "grayscale + alpha",
+                    6 => "RGBA",
+                    _ => "unknown colour type",
+                };
+                format!(
+                    "{} × {}, {}-bit {}{}",
+                    be32(0).unwrap_or(0),
+                    be32(4).unwrap_or(0),
+                    d[8],
+                    color,
+                    if d[12] == 1 { ", interlaced" } else { "" }
+                )
+            }
+            b"pHYs" if d.len() >= 9 => {
+                let (x, y) = (be32(0).unwrap_or(0), be32(4).unwrap_or(0));
+                if d[8] == 1 {
+                    // 每米像素数换算为每英寸
+                    format!("{:.1} × {:.1} DPI", x as f64 * 0.0254, y as f64 * 0.0254)
+                } else {
+                    format!("pixel aspect {}:{}", x, y)
+                }
+            }
+            b"gAMA" => be32(0).map(|g| format!("gamma {:.5}", g as f64 / 100_000.0)).unwrap_or_default(),
+            b"sRGB" => match d.first() {
+                Some(0) => "sRGB, perceptual".to_string(),
+                Some(1) => "sRGB, relative colorimetric".to_string(),
+                Some(2) => "sRGB, saturation".to_string(),
+                Some(3) => "sRGB, absolute colorimetric".to_string(),
+                _ => "sRGB".to_string(),
+            },
+            b"iCCP" => {
+                let name = d.iter().position(|&b| b == 0).map(|end| &d[..end]).unwrap_or(&[]);
+                format!("ICC profile \"{}\"", String::from_utf8_lossy(name))
+            }
+            b"tIME" if d.len() >= 7 => format!(
+                "modified {:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                u16::from_be_bytes([d[0], d[1]]),
+                d[2],
+                d[3],
+                d[4],
+                d[5],
+                d[6]
+            ),
+            b"PLTE" => format!("{} colours", d.len() / 3),
+            b"IDAT" => "image data".to_string(),
+            _ => String::new(),
+        }
+    }
+}
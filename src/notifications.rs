@@ -0,0 +1,211 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 消息级别
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn color(&self) -> egui::Color32 {
+        match self {
+            Level::Info => egui::Color32::from_rgb(120, 180, 255),
+            Level::Warning => egui::Color32::from_rgb(240, 190, 80),
+            Level::Error => egui::Color32::from_rgb(240, 90, 90),
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Level::Info => "ℹ",
+            Level::Warning => "⚠",
+            Level::Error => "✖",
+        }
+    }
+}
+
+/// 一条通知
+pub struct Notification {
+    pub level: Level,
+    pub text: String,
+    pub time: Instant,
+    shown_at: Option<Instant>,
+}
+
+// 各模块随时可以上报，主窗口每帧取走并显示
+static PENDING: Mutex<Vec<Notification>> = Mutex::new(Vec::new());
+
+fn push(level: Level, text: String) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.push(Notification {
+            level,
+            text,
+            time: Instant::now(),
+            shown_at: None,
+        });
+    }
+}
+
+/// 上报普通结果
+pub fn info(text: impl Into<String>) {
+    push(Level::Info, text.into());
+}
+
+/// 上报警告
+pub fn warn(text: impl Into<String>) {
+    push(Level::Warning, text.into());
+}
+
+/// 上报错误（文本中应带上具体原因）
+pub fn error(text: impl Into<String>) {
+    push(Level::Error, text.into());
+}
+
+/// 通知日志与浮动提示
+#[derive(Default)]
+pub struct Notifications {
+    pub show_log: bool,
+    log: Vec<Notification>,
+    // 日志窗口关闭期间新增的错误数
+    unread_errors: usize,
+}
+
+impl Notifications {
+    const MAX_LOG: usize = 500;
+    const MAX_TOASTS: usize = 5;
+
+    fn toast_duration(level: Level) -> Duration {
+        match level {
+            Level::Info => Duration::from_secs(4),
+            Level::Warning | Level::Error => Duration::from_secs(8),
+        }
+    }
+
+    /// 取走各模块上报的通知
+    pub fn collect(&mut self) {
+        let pending = match PENDING.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(_) => return,
+        };
+        for notification in pending {
+            if notification.level == Level::Error && !self.show_log {
+                self.unread_errors += 1;
+            }
+            self.log.push(notification);
+        }
+        if self.log.len() > Self::MAX_LOG {
+            self.log.drain(..self.log.len() - Self::MAX_LOG);
+        }
+    }
+
+    /// 最近一条通知（用于状态栏）
+    pub fn latest(&self) -> Option<&Notification> {
+        self.log.last()
+    }
+
+    pub fn unread_errors(&self) -> usize {
+        self.unread_errors
+    }
+
+    pub fn toggle_log(&mut self) {
+        self.show_log = !self.show_log;
+        self.unread_errors = 0;
+    }
+
+    /// 右下角浮动提示，到时自动消失，点击立即关闭
+    pub fn show_toasts(&mut self, ctx: &egui::Context, bottom_margin: f32) {
+        let now = Instant::now();
+        let mut offset = bottom_margin + 8.0;
+        let mut next_expiry: Option<Duration> = None;
+        let active = self
+            .log
+            .iter_mut()
+            .rev()
+            .filter(|n| n.shown_at.is_none_or(|t| now.duration_since(t) < Self::toast_duration(n.level)))
+            .take(Self::MAX_TOASTS);
+        for (i, notification) in active.enumerate() {
+            let shown_at = *notification.shown_at.get_or_insert(now);
+            let remaining = Self::toast_duration(notification.level).saturating_sub(now.duration_since(shown_at));
+            next_expiry = Some(next_expiry.map_or(remaining, |d| d.min(remaining)));
+
+            let response = egui::Area::new(egui::Id::new("toast").with(i))
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -offset))
+                .order(egui::Order::Foreground)
+                .interactable(true)
+                .show(ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(360.0);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(notification.level.color(), notification.level.icon());
+                            ui.label(&notification.text);
+                        });
+                    });
+                })
+                .response;
+            if response.interact(egui::Sense::click()).clicked() {
+                // 视为已过期
+                notification.shown_at = Some(now - Self::toast_duration(notification.level));
+            }
+            offset += response.rect.height() + 6.0;
+        }
+        if let Some(remaining) = next_expiry {
+            ctx.request_repaint_after(remaining);
+        }
+    }
+
+    /// 通知日志窗口
+    pub fn show_log_window(&mut self, ctx: &egui::Context) {
+        if !self.show_log {
+            return;
+        }
+        let mut show_log = self.show_log;
+        egui::Window::new("Notifications")
+            .open(&mut show_log)
+            .default_size([520.0, 320.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Clear").clicked() {
+                        self.log.clear();
+                    }
+                    if ui.button("Copy All").clicked() {
+                        let text = self
+                            .log
+                            .iter()
+                            .map(|n| format!("{} {} {}", Self::format_time(n.time), n.level.icon(), n.text))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.ctx().copy_text(text);
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for n in &self.log {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.monospace(Self::format_time(n.time));
+                                ui.colored_label(n.level.color(), n.level.icon());
+                                ui.label(&n.text);
+                            });
+                        }
+                    });
+            });
+        self.show_log = show_log;
+        self.unread_errors = 0;
+    }
+
+    /// 距今多久（不引入时区依赖）
+    fn format_time(time: Instant) -> String {
+        let secs = time.elapsed().as_secs();
+        match secs {
+            0..=59 => format!("{:>3}s ago", secs),
+            60..=3599 => format!("{:>2}m ago", secs / 60),
+            _ => format!("{:>2}h ago", secs / 3600),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+/// 像素网格（意匠纸）叠加层的状态
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PixelGrid {
+    pub enabled: bool,
+    // 缩放倍数达到此值才绘制网格
+    pub min_zoom: f32,
+    // 每隔多少像素绘制一条粗线
+    pub block_size: u32,
+    pub show_numbers: bool,
+    // 纬线从第一纬（底部）开始计数
+    pub origin_bottom_left: bool,
+}
+
+impl Default for PixelGrid {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_zoom: 4.0,
+            block_size: 8,
+            show_numbers: true,
+            origin_bottom_left: false,
+        }
+    }
+}
+
+impl PixelGrid {
+    /// 显示网格设置（用于菜单）
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Pixel Grid");
+        ui.horizontal(|ui| {
+            ui.label("Show from zoom");
+            ui.add(
+                egui::DragValue::new(&mut self.min_zoom)
+                    .speed(0.1)
+                    .range(1.0..=64.0)
+                    .suffix("x"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Block lines every");
+            ui.selectable_value(&mut self.block_size, 8, "8");
+            ui.selectable_value(&mut self.block_size, 10, "10");
+            ui.add(egui::DragValue::new(&mut self.block_size).range(2..=1000));
+        });
+        ui.checkbox(&mut self.show_numbers, "Row/column numbers");
+        ui.checkbox(&mut self.origin_bottom_left, "Origin at bottom-left");
+    }
+
+    /// 在图像上绘制像素网格、块线与行列编号
+    pub fn paint(&self, ui: &egui::Ui, image_rect: egui::Rect, image_size: [usize; 2], zoom: f32) {
+        if !self.enabled || zoom < self.min_zoom {
+            return;
+        }
+        let clip = ui.clip_rect();
+        let visible = image_rect.intersect(clip);
+        if !visible.is_positive() {
+            return;
+        }
+
+        let (width, height) = (image_size[0] as i64, image_size[1] as i64);
+        let first_x = (((visible.min.x - image_rect.min.x) / zoom).floor() as i64).clamp(0, width);
+        let last_x = (((visible.max.x - image_rect.min.x) / zoom).ceil() as i64).clamp(0, width);
+        let first_y = (((visible.min.y - image_rect.min.y) / zoom).floor() as i64).clamp(0, height);
+        let last_y = (((visible.max.y - image_rect.min.y) / zoom).ceil() as i64).clamp(0, height);
+
+        let painter = ui.painter_at(clip);
+        let thin = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(128, 128, 128, 110));
+        let thick = egui::Stroke::new(2.0, egui::Color32::from_rgba_unmultiplied(220, 60, 60, 200));
+        let block = self.block_size.max(1) as i64;
+
+        // 竖线：以列号计，第0列左边缘为起点
+        for x in first_x..=last_x {
+            let px = image_rect.min.x + x as f32 * zoom;
+            let stroke = if x % block == 0 { thick } else { thin };
+            painter.line_segment(
+                [egui::pos2(px, visible.min.y), egui::pos2(px, visible.max.y)],
+                stroke,
+            );
+        }
+        // 横线：底部为原点时按距离底边的行数判断块线
+        for y in first_y..=last_y {
+            let py = image_rect.min.y + y as f32 * zoom;
+            let counted = if self.origin_bottom_left { height - y } else { y };
+            let stroke = if counted % block == 0 { thick } else { thin };
+            painter.line_segment(
+                [egui::pos2(visible.min.x, py), egui::pos2(visible.max.x, py)],
+                stroke,
+            );
+        }
+
+        if !self.show_numbers {
+            return;
+        }
+
+        // 编号贴着可见区域的上边与左边，平移时始终可见
+        let font = egui::FontId::monospace(10.0);
+        let text_color = egui::Color32::from_rgb(220, 60, 60);
+        let background = egui::Color32::from_rgba_unmultiplied(255, 255, 255, 200);
+        for x in first_x..last_x {
+            let number = x + 1;
+            if number % block != 0 && number != 1 {
+                continue;
+            }
+            let center = egui::pos2(image_rect.min.x + (x as f32 + 0.5) * zoom, visible.min.y + 2.0);
+            let galley = painter.layout_no_wrap(number.to_string(), font.clone(), text_color);
+            let rect = egui::Align2::CENTER_TOP.anchor_size(center, galley.size());
+            painter.rect_filled(rect.expand(1.0), 2.0, background);
+            painter.galley(rect.min, galley, text_color);
+        }
+        for y in first_y..last_y {
+            let number = if self.origin_bottom_left { height - y } else { y + 1 };
+            if number % block != 0 && number != 1 {
+                continue;
+            }
+            let center = egui::pos2(visible.min.x + 2.0, image_rect.min.y + (y as f32 + 0.5) * zoom);
+            let galley = painter.layout_no_wrap(number.to_string(), font.clone(), text_color);
+            let rect = egui::Align2::LEFT_CENTER.anchor_size(center, galley.size());
+            painter.rect_filled(rect.expand(1.0), 2.0, background);
+            painter.galley(rect.min, galley, text_color);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::color_reflection_window::ColorReflectionWindow;
+use crate::utils::{GrayscaleMode, ImageProcessor};
+use image::{DynamicImage, GenericImageView};
+
+/// 像素检查器的状态：显示鼠标下像素的原色、灰度与所属区段
+pub struct PixelInspector {
+    pub show_window: bool,
+    // 点击图像时把最近的锚点移到该像素的灰度值
+    pub click_sets_anchor: bool,
+    // 鼠标下的图像坐标（以原图像素计）
+    hovered: Option<(u32, u32)>,
+}
+
+impl Default for PixelInspector {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            click_sets_anchor: true,
+            hovered: None,
+        }
+    }
+}
+
+impl PixelInspector {
+    /// 是否接管主显示区的左键点击
+    pub fn is_active(&self) -> bool {
+        self.show_window && self.click_sets_anchor
+    }
+
+    /// 根据鼠标位置更新当前检查的像素
+    pub fn track_hover(
+        &mut self,
+        response: &egui::Response,
+        image_rect: egui::Rect,
+        image_size: Option<(u32, u32)>,
+    ) {
+        self.hovered = match (response.hover_pos(), image_size) {
+            (Some(pos), Some((w, h))) => {
+                let rel = (pos - image_rect.min) / image_rect.size();
+                let x = (rel.x * w as f32).floor();
+                let y = (rel.y * h as f32).floor();
+                if x >= 0.0 && y >= 0.0 && (x as u32) < w && (y as u32) < h {
+                    Some((x as u32, y as u32))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+    }
+
+    /// 点击时将最近的锚点移到鼠标下像素的灰度值
+    pub fn handle_click(
+        &self,
+        response: &egui::Response,
+        original_image: &Option<DynamicImage>,
+        grayscale_mode: GrayscaleMode,
+        color_reflection_window: &mut ColorReflectionWindow,
+    ) {
+        if !response.clicked_by(egui::PointerButton::Primary) {
+            return;
+        }
+        if let (Some((x, y)), Some(img)) = (self.hovered, original_image) {
+            let pixel = Self::pixel_at(img, x, y);
+            if let Some(pixel) = pixel {
+                let gray = ImageProcessor::pixel_gray(pixel, grayscale_mode);
+                color_reflection_window.place_anchor(gray as f32);
+                color_reflection_window.show_window = true;
+            }
+        }
+    }
+
+    /// 读取原图像素（越界时返回None）
+    fn pixel_at(img: &DynamicImage, x: u32, y: u32) -> Option<[u8; 4]> {
+        (x < img.width() && y < img.height()).then(|| img.get_pixel(x, y).0)
+    }
+
+    /// 显示检查器窗口
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        original_image: &Option<DynamicImage>,
+        grayscale_mode: GrayscaleMode,
+        color_reflection_window: &ColorReflectionWindow,
+    ) {
+        if !self.show_window {
+            return;
+        }
+        let mut show_window = self.show_window;
+        egui::Window::new("Pixel Inspector")
+            .open(&mut show_window)
+            .default_width(260.0)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut self.click_sets_anchor, "Click to move nearest anchor");
+                ui.separator();
+
+                let pixel = self.hovered.and_then(|(x, y)| {
+                    original_image
+                        .as_ref()
+                        .and_then(|img| Self::pixel_at(img, x, y))
+                        .map(|p| (x, y, p))
+                });
+                let Some((x, y, rgba)) = pixel else {
+                    ui.label("Hover over the image to inspect a pixel");
+                    return;
+                };
+
+                egui::Grid::new("pixel_inspector_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Position");
+                        ui.label(format!("{}, {}", x, y));
+                        ui.end_row();
+
+                        ui.label("Original RGBA");
+                        ui.horizontal(|ui| {
+                            let (swatch, _) =
+                                ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+                            ui.painter().rect_filled(
+                                swatch,
+                                2.0,
+                                egui::Color32::from_rgba_unmultiplied(rgba[0], rgba[1], rgba[2], rgba[3]),
+                            );
+                            ui.label(format!("{}, {}, {}, {}", rgba[0], rgba[1], rgba[2], rgba[3]));
+                        });
+                        ui.end_row();
+
+                        for (mode, name) in [
+                            (GrayscaleMode::Default, "Gray (default)"),
+                            (GrayscaleMode::Max, "Gray (max)"),
+                            (GrayscaleMode::Min, "Gray (min)"),
+                        ] {
+                            let gray = ImageProcessor::pixel_gray(rgba, mode);
+                            if mode == grayscale_mode {
+                                ui.strong(name);
+                                ui.strong(gray.to_string());
+                            } else {
+                                ui.label(name);
+                                ui.label(gray.to_string());
+                            }
+                            ui.end_row();
+                        }
+
+                        let gray = ImageProcessor::pixel_gray(rgba, grayscale_mode);
+                        let inspected = if rgba[3] == 0 {
+                            None
+                        } else {
+                            color_reflection_window.inspect_gray(gray)
+                        };
+                        ui.label("Segment");
+                        match inspected {
+                            Some((segment, _)) => ui.label(format!("{}", segment + 1)),
+                            None if rgba[3] == 0 => ui.label("transparent"),
+                            None => ui.label("no anchors"),
+                        };
+                        ui.end_row();
+
+                        ui.label("Output");
+                        match inspected {
+                            Some((_, output)) => ui.label(output.to_string()),
+                            None => ui.label("-"),
+                        };
+                        ui.end_row();
+                    });
+            });
+        self.show_window = show_window;
+    }
+}
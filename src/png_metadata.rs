@@ -0,0 +1,695 @@
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+
+/// PNG文件签名
+pub const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+// 超过该长度的文本压缩保存（zTXt / 压缩的iTXt）
+const COMPRESS_THRESHOLD: usize = 1024;
+// 解压文本的上限，防止恶意文件展开成巨量数据
+const MAX_TEXT_SIZE: u64 = 16 * 1024 * 1024;
+// 规范允许的最大块长度
+const MAX_CHUNK_LENGTH: u32 = i32::MAX as u32;
+
+/// 一个PNG数据块（不含长度字段与CRC）
+///
+/// 默认读取时图像数据块（IDAT、fdAT）只校验不载入，data 为空而 length 为实际长度
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    pub kind: [u8; 4],
+    pub length: u32,
+    pub data: Vec<u8>,
+}
+
+/// 读取PNG数据块时的错误
+#[derive(Debug)]
+pub enum ChunkError {
+    Io(std::io::Error),
+    NotPng,
+    // 文件在某个块（或IEND之前）意外结束
+    Truncated(String),
+    CrcMismatch { kind: String, offset: u64 },
+    InvalidLength { kind: String, length: u32 },
+    InvalidText { keyword: String, reason: String },
+}
+
+impl std::fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkError::Io(e) => write!(f, "{}", e),
+            ChunkError::NotPng => write!(f, "not a PNG file"),
+            ChunkError::Truncated(what) => write!(f, "file is truncated ({})", what),
+            ChunkError::CrcMismatch { kind, offset } => {
+                write!(f, "{} chunk at byte {} is corrupt (CRC mismatch)", kind, offset)
+            }
+            ChunkError::InvalidLength { kind, length } => write!(f, "{} chunk has invalid length {}", kind, length),
+            ChunkError::InvalidText { keyword, reason } => write!(f, "text entry '{}' is corrupt: {}", keyword, reason),
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {}
+
+impl From<std::io::Error> for ChunkError {
+    fn from(e: std::io::Error) -> Self {
+        ChunkError::Io(e)
+    }
+}
+
+/// 文本块类型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextKind {
+    // tEXt：Latin-1，不压缩
+    Latin1,
+    // zTXt：Latin-1，zlib压缩
+    Compressed,
+    // iTXt：UTF-8，可选压缩
+    International,
+}
+
+/// 解码后的文本条目
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEntry {
+    pub kind: TextKind,
+    pub keyword: String,
+    // iTXt的语言标签与翻译后的关键字（其他类型为空）
+    pub language: String,
+    pub translated_keyword: String,
+    pub text: String,
+    // 是否位于图像数据之后（重写文件时保持原位置）
+    pub after_image_data: bool,
+}
+
+impl TextEntry {
+    /// 新条目（写在图像数据之前，按内容选择块类型）
+    pub fn new(keyword: &str, text: &str) -> Self {
+        Self {
+            kind: TextKind::Latin1,
+            keyword: keyword.to_string(),
+            language: String::new(),
+            translated_keyword: String::new(),
+            text: text.to_string(),
+            after_image_data: false,
+        }
+    }
+}
+
+impl Chunk {
+    pub fn new(kind: &[u8; 4], data: Vec<u8>) -> Self {
+        Self {
+            kind: *kind,
+            length: data.len() as u32,
+            data,
+        }
+    }
+
+    /// 首字母大写为关键块（IHDR、PLTE、IDAT、IEND）
+    pub fn is_critical(&self) -> bool {
+        self.kind[0].is_ascii_uppercase()
+    }
+
+    /// 第四个字母小写表示图像数据改变后仍可原样复制
+    pub fn is_safe_to_copy(&self) -> bool {
+        self.kind[3].is_ascii_lowercase()
+    }
+
+    pub fn kind_name(&self) -> String {
+        kind_name(&self.kind)
+    }
+
+    pub fn text_kind(&self) -> Option<TextKind> {
+        match &self.kind {
+            b"tEXt" => Some(TextKind::Latin1),
+            b"zTXt" => Some(TextKind::Compressed),
+            b"iTXt" => Some(TextKind::International),
+            _ => None,
+        }
+    }
+
+    /// 文本块的关键字（无需解压）
+    pub fn keyword(&self) -> Option<String> {
+        self.text_kind()?;
+        let end = self.data.iter().position(|&b| b == 0)?;
+        Some(latin1(&self.data[..end]))
+    }
+
+    /// 解码文本块；不是文本块时返回None
+    pub fn decode_text(&self) -> Option<Result<TextEntry, String>> {
+        let kind = self.text_kind()?;
+        Some(Self::decode_text_data(kind, &self.data))
+    }
+
+    fn decode_text_data(kind: TextKind, data: &[u8]) -> Result<TextEntry, String> {
+        let end = data.iter().position(|&b| b == 0).ok_or("missing keyword separator")?;
+        if end == 0 || end > 79 {
+            return Err(format!("invalid keyword length {}", end));
+        }
+        let keyword = latin1(&data[..end]);
+        let rest = &data[end + 1..];
+        let mut language = String::new();
+        let mut translated_keyword = String::new();
+        let text = match kind {
+            TextKind::Latin1 => latin1(rest),
+            TextKind::Compressed => {
+                let (&method, compressed) = rest.split_first().ok_or("missing compression method")?;
+                if method != 0 {
+                    return Err(format!("unknown compression method {}", method));
+                }
+                latin1(&inflate(compressed)?)
+            }
+            TextKind::International => {
+                let [flag, method, rest @ ..] = rest else {
+                    return Err("missing compression flag".to_string());
+                };
+                // 语言标签与翻译后的关键字，各以0结尾
+                let lang_end = rest.iter().position(|&b| b == 0).ok_or("missing language tag")?;
+                language = latin1(&rest[..lang_end]);
+                let rest = &rest[lang_end + 1..];
+                let translated_end = rest.iter().position(|&b| b == 0).ok_or("missing translated keyword")?;
+                translated_keyword = String::from_utf8(rest[..translated_end].to_vec())
+                    .map_err(|_| "translated keyword is not valid UTF-8".to_string())?;
+                let text = &rest[translated_end + 1..];
+                let bytes = match (flag, method) {
+                    (0, _) => text.to_vec(),
+                    (1, 0) => inflate(text)?,
+                    (1, m) => return Err(format!("unknown compression method {}", m)),
+                    (f, _) => return Err(format!("invalid compression flag {}", f)),
+                };
+                String::from_utf8(bytes).map_err(|_| "text is not valid UTF-8".to_string())?
+            }
+        };
+        Ok(TextEntry {
+            kind,
+            keyword,
+            language,
+            translated_keyword,
+            text,
+            after_image_data: false,
+        })
+    }
+
+    /// 编码文本块：能用Latin-1表示时写tEXt（较长时zTXt），否则写UTF-8的iTXt
+    pub fn encode_text(keyword: &str, text: &str) -> Result<Chunk, String> {
+        Self::encode_entry(&TextEntry::new(keyword, text))
+    }
+
+    /// 编码文本条目；带语言标签或翻译关键字的条目总是写iTXt以保留这两项
+    pub fn encode_entry(entry: &TextEntry) -> Result<Chunk, String> {
+        let mut data = to_latin1(&entry.keyword).ok_or("keyword must be Latin-1")?;
+        if data.is_empty() || data.len() > 79 || data.contains(&0) {
+            return Err("keyword must be 1-79 characters without NUL".to_string());
+        }
+        data.push(0);
+        let text = entry.text.as_str();
+        let compress = text.len() > COMPRESS_THRESHOLD;
+        let international = !entry.language.is_empty() || !entry.translated_keyword.is_empty();
+        match to_latin1(text).filter(|_| !international) {
+            Some(bytes) if !compress => {
+                data.extend_from_slice(&bytes);
+                Ok(Chunk::new(b"tEXt", data))
+            }
+            Some(bytes) => {
+                data.push(0);
+                data.extend_from_slice(&deflate(&bytes)?);
+                Ok(Chunk::new(b"zTXt", data))
+            }
+            None => {
+                // 压缩标志、压缩方法、语言标签、翻译后的关键字
+                if entry.language.contains('\0') || entry.translated_keyword.contains('\0') {
+                    return Err("language tag and translated keyword must not contain NUL".to_string());
+                }
+                data.extend_from_slice(&[compress as u8, 0]);
+                data.extend_from_slice(&to_latin1(&entry.language).ok_or("language tag must be Latin-1")?);
+                data.push(0);
+                data.extend_from_slice(entry.translated_keyword.as_bytes());
+                data.push(0);
+                if compress {
+                    data.extend_from_slice(&deflate(text.as_bytes())?);
+                } else {
+                    data.extend_from_slice(text.as_bytes());
+                }
+                Ok(Chunk::new(b"iTXt", data))
+            }
+        }
+    }
+}
+
+/// 逐块流式读取PNG并校验CRC，读到IEND为止；出错后不再产出
+pub struct ChunkReader<R: Read> {
+    reader: R,
+    offset: u64,
+    done: bool,
+    load_image_data: bool,
+}
+
+impl<R: Read> ChunkReader<R> {
+    /// 检查文件签名
+    pub fn new(mut reader: R) -> Result<Self, ChunkError> {
+        let mut signature = [0u8; 8];
+        match reader.read_exact(&mut signature) {
+            Ok(()) if signature == SIGNATURE => {}
+            Ok(()) => return Err(ChunkError::NotPng),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Err(ChunkError::NotPng),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(Self {
+            reader,
+            offset: SIGNATURE.len() as u64,
+            done: false,
+            load_image_data: false,
+        })
+    }
+
+    /// 同时载入图像数据块（原样重写文件时需要）
+    pub fn with_image_data(mut self) -> Self {
+        self.load_image_data = true;
+        self
+    }
+
+    fn read_chunk(&mut self) -> Result<Chunk, ChunkError> {
+        let mut header = [0u8; 8];
+        self.read_exact(&mut header, "missing IEND")?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let kind = [header[4], header[5], header[6], header[7]];
+        if length > MAX_CHUNK_LENGTH {
+            return Err(ChunkError::InvalidLength { kind: kind_name(&kind), length });
+        }
+        let start = self.offset;
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&kind);
+        let keep = self.load_image_data || !matches!(&kind, b"IDAT" | b"fdAT");
+        let mut data = Vec::new();
+        let mut remaining = length as usize;
+        // 按块大小分段读取：截断的文件不会因伪造的长度预先分配大量内存
+        let mut buf = [0u8; 8192];
+        while remaining > 0 {
+            let n = remaining.min(buf.len());
+            self.read_exact(&mut buf[..n], &format!("in {} chunk", kind_name(&kind)))?;
+            hasher.update(&buf[..n]);
+            if keep {
+                data.extend_from_slice(&buf[..n]);
+            }
+            remaining -= n;
+        }
+
+        let mut crc = [0u8; 4];
+        self.read_exact(&mut crc, &format!("in {} chunk", kind_name(&kind)))?;
+        if u32::from_be_bytes(crc) != hasher.finalize() {
+            return Err(ChunkError::CrcMismatch { kind: kind_name(&kind), offset: start - 8 });
+        }
+        Ok(Chunk { kind, length, data })
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8], what: &str) -> Result<(), ChunkError> {
+        match self.reader.read_exact(buf) {
+            Ok(()) => {
+                self.offset += buf.len() as u64;
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Err(ChunkError::Truncated(what.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = Result<Chunk, ChunkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_chunk();
+        self.done = result.as_ref().map_or(true, |c| &c.kind == b"IEND");
+        Some(result)
+    }
+}
+
+/// 读取文件的全部数据块（图像数据不载入）
+pub fn read_chunks_from_path(path: &Path) -> Result<Vec<Chunk>, ChunkError> {
+    let file = std::fs::File::open(path)?;
+    ChunkReader::new(BufReader::new(file))?.collect()
+}
+
+/// 读取文件的全部数据块，包括图像数据
+pub fn read_chunks_with_image_data(path: &Path) -> Result<Vec<Chunk>, ChunkError> {
+    let file = std::fs::File::open(path)?;
+    ChunkReader::new(BufReader::new(file))?.with_image_data().collect()
+}
+
+/// 按原样写出数据块（含签名，逐块计算CRC）
+pub fn write_chunks<W: Write>(w: &mut W, chunks: &[Chunk]) -> std::io::Result<()> {
+    w.write_all(&SIGNATURE)?;
+    for chunk in chunks {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&chunk.kind);
+        hasher.update(&chunk.data);
+        w.write_all(&(chunk.data.len() as u32).to_be_bytes())?;
+        w.write_all(&chunk.kind)?;
+        w.write_all(&chunk.data)?;
+        w.write_all(&hasher.finalize().to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// 用给定的文本条目替换全部文本块；原在图像数据之后的条目写回IDAT之后，其余放在第一个IDAT之前
+pub fn replace_text_entries(chunks: &[Chunk], entries: &[TextEntry]) -> Result<Vec<Chunk>, String> {
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    for entry in entries {
+        let chunk = Chunk::encode_entry(entry).map_err(|e| format!("'{}': {}", entry.keyword, e))?;
+        if entry.after_image_data {
+            after.push(chunk);
+        } else {
+            before.push(chunk);
+        }
+    }
+    let mut out = Vec::with_capacity(chunks.len() + before.len() + after.len());
+    let mut seen_image_data = false;
+    for chunk in chunks.iter().filter(|c| c.text_kind().is_none()) {
+        if &chunk.kind == b"IDAT" {
+            seen_image_data = true;
+            out.append(&mut before);
+        } else if seen_image_data {
+            out.append(&mut after);
+        }
+        if &chunk.kind == b"IEND" {
+            // 没有IDAT的文件也不丢条目
+            out.append(&mut before);
+            out.append(&mut after);
+        }
+        out.push(chunk.clone());
+    }
+    Ok(out)
+}
+
+/// 解码块列表中的全部文本条目并标记位置；损坏的文本块跳过，返回条目与损坏块数
+pub fn decode_text_entries(chunks: &[Chunk]) -> (Vec<TextEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut corrupt = 0;
+    let mut seen_image_data = false;
+    for chunk in chunks {
+        seen_image_data |= &chunk.kind == b"IDAT";
+        match chunk.decode_text() {
+            Some(Ok(mut entry)) => {
+                entry.after_image_data = seen_image_data;
+                entries.push(entry);
+            }
+            Some(Err(_)) => corrupt += 1,
+            None => {}
+        }
+    }
+    (entries, corrupt)
+}
+
+/// 去掉全部元数据，只留关键块与透明度
+pub fn strip_metadata(chunks: &[Chunk]) -> Vec<Chunk> {
+    chunks
+        .iter()
+        .filter(|c| c.is_critical() || &c.kind == b"tRNS")
+        .cloned()
+        .collect()
+}
+
+/// 列出文件中的全部文本条目（tEXt、zTXt、iTXt，按文件中的顺序）
+pub fn read_text_entries(path: &Path) -> Result<Vec<TextEntry>, ChunkError> {
+    let file = std::fs::File::open(path)?;
+    text_entries(ChunkReader::new(BufReader::new(file))?)
+}
+
+/// 从数据块流中解码全部文本条目；任一块损坏时报错而不是返回部分结果
+pub fn text_entries<R: Read>(reader: ChunkReader<R>) -> Result<Vec<TextEntry>, ChunkError> {
+    let mut entries = Vec::new();
+    let mut seen_image_data = false;
+    for chunk in reader {
+        let chunk = chunk?;
+        seen_image_data |= &chunk.kind == b"IDAT";
+        if let Some(entry) = chunk.decode_text() {
+            let mut entry = entry.map_err(|reason| ChunkError::InvalidText {
+                keyword: chunk.keyword().unwrap_or_default(),
+                reason,
+            })?;
+            entry.after_image_data = seen_image_data;
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// 从源文件的数据块中挑出重新编码为RGBA后仍然有效的附属块，按相对IDAT的位置分为前后两组
+///
+/// skip_keyword 指定的文本条目不保留（由调用方写入新值）
+pub fn carried_chunks(source: &[Chunk], skip_keyword: Option<&str>) -> (Vec<Chunk>, Vec<Chunk>) {
+    // 灰度源图的ICC配置文件不适用于RGBA输出
+    let gray_source = source
+        .iter()
+        .find(|c| &c.kind == b"IHDR")
+        .and_then(|c| c.data.get(9))
+        .is_some_and(|&color_type| color_type == 0 || color_type == 4);
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let mut seen_idat = false;
+    for chunk in source {
+        if &chunk.kind == b"IDAT" {
+            seen_idat = true;
+        }
+        if chunk.is_critical() || !keep_after_reencode(chunk, gray_source) {
+            continue;
+        }
+        if skip_keyword.is_some() && chunk.keyword().as_deref() == skip_keyword {
+            continue;
+        }
+        if seen_idat {
+            after.push(chunk.clone());
+        } else {
+            before.push(chunk.clone());
+        }
+    }
+    (before, after)
+}
+
+fn keep_after_reencode(chunk: &Chunk, gray_source: bool) -> bool {
+    match &chunk.kind {
+        // 色彩空间信息与像素格式无关
+        b"gAMA" | b"cHRM" | b"sRGB" | b"cICP" | b"tIME" => true,
+        b"iCCP" => !gray_source,
+        // 打开时已按EXIF方向旋转过，保留会被查看器再转一次
+        b"eXIf" => false,
+        // tRNS、bKGD、sBIT、hIST等依赖原像素格式，以及其他不可复制的块
+        _ => chunk.is_safe_to_copy(),
+    }
+}
+
+fn kind_name(kind: &[u8; 4]) -> String {
+    String::from_utf8_lossy(kind).into_owned()
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn to_latin1(text: &str) -> Option<Vec<u8>> {
+    text.chars().map(|c| u8::try_from(c as u32).ok()).collect()
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    ZlibDecoder::new(data)
+        .take(MAX_TEXT_SIZE + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("corrupt compressed text: {}", e))?;
+    if out.len() as u64 > MAX_TEXT_SIZE {
+        return Err("compressed text is too large".to_string());
+    }
+    Ok(out)
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    // 2x2灰度图：tEXt、zTXt、iTXt各一条在IDAT前，另一条文本在IDAT后
+    fn sample_png() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 2);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.add_text_chunk("Author".to_string(), "Weaver".to_string()).unwrap();
+        encoder.add_ztxt_chunk("Comment".to_string(), "x".repeat(300)).unwrap();
+        encoder.add_itxt_chunk("Title".to_string(), "花纹".to_string()).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0, 64, 128, 255]).unwrap();
+        let trailing = Chunk::encode_text("anchors", "{}").unwrap();
+        writer.write_chunk(png::chunk::ChunkType(trailing.kind), &trailing.data).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    fn entries(bytes: &[u8]) -> Result<Vec<TextEntry>, ChunkError> {
+        text_entries(ChunkReader::new(Cursor::new(bytes))?)
+    }
+
+    #[test]
+    fn lists_all_text_entries() {
+        let entries = entries(&sample_png()).unwrap();
+        let summary: Vec<_> = entries.iter().map(|e| (e.kind, e.keyword.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (TextKind::Latin1, "Author"),
+                (TextKind::Compressed, "Comment"),
+                (TextKind::International, "Title"),
+                (TextKind::Latin1, "anchors"),
+            ]
+        );
+        assert_eq!(entries[1].text, "x".repeat(300));
+        assert_eq!(entries[2].text, "花纹");
+    }
+
+    #[test]
+    fn skips_image_data_but_reports_its_length() {
+        let chunks: Vec<Chunk> = ChunkReader::new(Cursor::new(sample_png())).unwrap().collect::<Result<_, _>>().unwrap();
+        let idat = chunks.iter().find(|c| &c.kind == b"IDAT").unwrap();
+        assert!(idat.data.is_empty());
+        assert!(idat.length > 0);
+        assert_eq!(&chunks.last().unwrap().kind, b"IEND");
+    }
+
+    #[test]
+    fn reports_crc_mismatch() {
+        let mut bytes = sample_png();
+        // IHDR数据的第一个字节
+        bytes[16] ^= 0xff;
+        match entries(&bytes) {
+            Err(ChunkError::CrcMismatch { kind, offset }) => {
+                assert_eq!(kind, "IHDR");
+                assert_eq!(offset, 8);
+            }
+            other => panic!("expected CRC mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_non_png() {
+        assert!(matches!(entries(b"GIF89a"), Err(ChunkError::NotPng)));
+        assert!(matches!(entries(b""), Err(ChunkError::NotPng)));
+    }
+
+    #[test]
+    fn rejects_oversized_length_without_allocating() {
+        let mut bytes = SIGNATURE.to_vec();
+        bytes.extend_from_slice(&0x7fff_fff0u32.to_be_bytes());
+        bytes.extend_from_slice(b"tEXt");
+        assert!(matches!(entries(&bytes), Err(ChunkError::Truncated(_))));
+        bytes[8] = 0xff;
+        assert!(matches!(entries(&bytes), Err(ChunkError::InvalidLength { .. })));
+    }
+
+    #[test]
+    fn rejects_corrupt_compressed_text() {
+        let mut data = b"Comment\0\0".to_vec();
+        data.extend_from_slice(b"not zlib");
+        let chunk = Chunk::new(b"zTXt", data);
+        assert!(chunk.decode_text().unwrap().is_err());
+    }
+
+    #[test]
+    fn rewrites_text_entries_and_strips_metadata() {
+        let chunks: Vec<Chunk> = ChunkReader::new(Cursor::new(sample_png()))
+            .unwrap()
+            .with_image_data()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let edited = replace_text_entries(&chunks, &[TextEntry::new("Title", "新花纹")]).unwrap();
+        let mut bytes = Vec::new();
+        write_chunks(&mut bytes, &edited).unwrap();
+        let entries = entries(&bytes).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].text, "新花纹");
+        assert!(image::load_from_memory(&bytes).is_ok());
+
+        let mut stripped = Vec::new();
+        write_chunks(&mut stripped, &strip_metadata(&edited)).unwrap();
+        let kinds: Vec<[u8; 4]> = ChunkReader::new(Cursor::new(&stripped)).unwrap().map(|c| c.unwrap().kind).collect();
+        assert_eq!(kinds, vec![*b"IHDR", *b"IDAT", *b"IEND"]);
+    }
+
+    #[test]
+    fn rewriting_keeps_language_fields_and_placement() {
+        let chunks: Vec<Chunk> = ChunkReader::new(Cursor::new(sample_png()))
+            .unwrap()
+            .with_image_data()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let (mut edited, corrupt) = decode_text_entries(&chunks);
+        assert_eq!(corrupt, 0);
+        assert!(edited[3].after_image_data);
+        edited[2].language = "zh-CN".to_string();
+        edited[2].translated_keyword = "标题".to_string();
+        let mut bytes = Vec::new();
+        write_chunks(&mut bytes, &replace_text_entries(&chunks, &edited).unwrap()).unwrap();
+        // 块类型按内容重新选择，其余字段与位置不变
+        let written = entries(&bytes).unwrap();
+        assert_eq!(written.len(), edited.len());
+        for (a, b) in written.iter().zip(&edited) {
+            assert_eq!(TextEntry { kind: b.kind, ..a.clone() }, *b);
+        }
+        let kinds: Vec<[u8; 4]> = ChunkReader::new(Cursor::new(&bytes)).unwrap().map(|c| c.unwrap().kind).collect();
+        assert_eq!(kinds[4..], [*b"IDAT", *b"tEXt", *b"IEND"]);
+
+        // 带语言标签的Latin-1文本也写成iTXt
+        let mut latin = TextEntry::new("Author", "Weaver");
+        latin.language = "en".to_string();
+        assert_eq!(&Chunk::encode_entry(&latin).unwrap().kind, b"iTXt");
+    }
+
+    proptest! {
+        #[test]
+        fn truncated_files_are_errors(cut in 0usize..10_000) {
+            let bytes = sample_png();
+            let cut = cut % bytes.len();
+            prop_assert!(entries(&bytes[..cut]).is_err());
+        }
+
+        #[test]
+        fn any_corrupted_byte_is_detected(pos in 0usize..10_000, flip in 1u8..=255) {
+            let mut bytes = sample_png();
+            let pos = pos % bytes.len();
+            bytes[pos] ^= flip;
+            prop_assert!(entries(&bytes).is_err());
+        }
+
+        #[test]
+        fn arbitrary_chunk_streams_do_not_panic(body in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let mut bytes = SIGNATURE.to_vec();
+            bytes.extend_from_slice(&body);
+            let _ = entries(&bytes);
+        }
+
+        #[test]
+        fn arbitrary_text_chunk_data_does_not_panic(
+            kind in prop_oneof![Just(*b"tEXt"), Just(*b"zTXt"), Just(*b"iTXt")],
+            data in proptest::collection::vec(any::<u8>(), 0..256),
+        ) {
+            let _ = Chunk::new(&kind, data).decode_text();
+        }
+
+        #[test]
+        fn text_round_trips(keyword in "[A-Za-z][A-Za-z0-9 ]{0,78}", text in "\\PC{0,2000}") {
+            let chunk = Chunk::encode_text(&keyword, &text).unwrap();
+            let entry = chunk.decode_text().unwrap().unwrap();
+            prop_assert_eq!(entry.keyword, keyword);
+            prop_assert_eq!(entry.text, text);
+        }
+    }
+}
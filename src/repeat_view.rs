@@ -0,0 +1,163 @@
+use crate::utils::{ImageProcessor, RepeatArrangement};
+use std::path::PathBuf;
+
+/// 循环平铺预览的状态
+pub struct RepeatView {
+    pub enabled: bool,
+    pub arrangement: RepeatArrangement,
+    pub seam_check: bool,
+    pub seam_tolerance: u8,
+    cache: Option<RepeatCache>,
+}
+
+/// 由当前图像生成的循环单元缓存（当前纹理或排列方式变化时重建）
+struct RepeatCache {
+    source: egui::TextureId,
+    arrangement: RepeatArrangement,
+    tolerance: u8,
+    texture: egui::TextureHandle,
+    unit_size: egui::Vec2,
+    // 右边缘不匹配的行、下边缘不匹配的列（已合并为连续区间）
+    seam_rows: Vec<(u32, u32)>,
+    seam_cols: Vec<(u32, u32)>,
+}
+
+impl Default for RepeatView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            arrangement: RepeatArrangement::Straight,
+            seam_check: false,
+            seam_tolerance: 0,
+            cache: None,
+        }
+    }
+}
+
+impl RepeatArrangement {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RepeatArrangement::Straight => "Straight",
+            RepeatArrangement::HalfDrop => "Half-drop",
+            RepeatArrangement::HalfBrick => "Half-brick",
+            RepeatArrangement::Mirror => "Mirror",
+        }
+    }
+}
+
+impl RepeatView {
+    // 防止缩得很小时绘制过多的平铺块
+    const MAX_TILES: i64 = 4096;
+
+    /// 当前循环单元的拼接不连续数量（右边缘行数 + 下边缘列数）
+    pub fn seam_count(&self) -> Option<usize> {
+        let cache = self.cache.as_ref()?;
+        let count = |ranges: &[(u32, u32)]| ranges.iter().map(|(a, b)| (b - a) as usize).sum::<usize>();
+        Some(count(&cache.seam_rows) + count(&cache.seam_cols))
+    }
+
+    /// 确保缓存与当前纹理、排列方式一致
+    fn ensure_cache(
+        &mut self,
+        ctx: &egui::Context,
+        source: &egui::TextureHandle,
+        temp_path: &Option<PathBuf>,
+    ) {
+        let up_to_date = self.cache.as_ref().is_some_and(|c| {
+            c.source == source.id()
+                && c.arrangement == self.arrangement
+                && c.tolerance == self.seam_tolerance
+        });
+        if up_to_date {
+            return;
+        }
+        self.cache = None;
+
+        let Some(img) = temp_path.as_ref().and_then(|p| image::open(p).ok()) else {
+            return;
+        };
+        let unit = ImageProcessor::build_repeat_unit(&img, self.arrangement);
+        let (rows, cols) = ImageProcessor::seam_mismatches(&unit, self.seam_tolerance);
+        self.cache = Some(RepeatCache {
+            source: source.id(),
+            arrangement: self.arrangement,
+            tolerance: self.seam_tolerance,
+            texture: ImageProcessor::update_texture_from_image(&unit, ctx),
+            unit_size: egui::vec2(unit.width() as f32, unit.height() as f32),
+            seam_rows: Self::to_ranges(&rows),
+            seam_cols: Self::to_ranges(&cols),
+        });
+    }
+
+    /// 将有序下标合并为 [start, end) 区间，减少绘制的图形数量
+    fn to_ranges(indices: &[u32]) -> Vec<(u32, u32)> {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for &i in indices {
+            match ranges.last_mut() {
+                Some(last) if last.1 == i => last.1 = i + 1,
+                _ => ranges.push((i, i + 1)),
+            }
+        }
+        ranges
+    }
+
+    /// 在显示区域内平铺循环单元，origin为其中一块的左上角
+    pub fn paint(
+        &mut self,
+        ui: &egui::Ui,
+        source: &egui::TextureHandle,
+        temp_path: &Option<PathBuf>,
+        origin: egui::Pos2,
+        zoom: f32,
+    ) {
+        self.ensure_cache(ui.ctx(), source, temp_path);
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        let clip = ui.clip_rect();
+        let tile = cache.unit_size * zoom;
+        if tile.x < 0.5 || tile.y < 0.5 {
+            return;
+        }
+        let first_col = ((clip.min.x - origin.x) / tile.x).floor() as i64;
+        let last_col = ((clip.max.x - origin.x) / tile.x).ceil() as i64;
+        let first_row = ((clip.min.y - origin.y) / tile.y).floor() as i64;
+        let last_row = ((clip.max.y - origin.y) / tile.y).ceil() as i64;
+        if (last_col - first_col) * (last_row - first_row) > Self::MAX_TILES {
+            return;
+        }
+
+        let painter = ui.painter_at(clip);
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let seam_color = egui::Color32::from_rgba_unmultiplied(255, 0, 0, 200);
+        let seam_width = zoom.clamp(2.0, 6.0);
+
+        for row in first_row..last_row {
+            for col in first_col..last_col {
+                let min = origin + egui::vec2(col as f32 * tile.x, row as f32 * tile.y);
+                let rect = egui::Rect::from_min_size(min, tile);
+                painter.image(cache.texture.id(), rect, uv, egui::Color32::WHITE);
+
+                if self.seam_check {
+                    // 右边缘：与下一块的左边缘不连续的行
+                    for &(start, end) in &cache.seam_rows {
+                        let seam = egui::Rect::from_min_max(
+                            egui::pos2(rect.max.x - seam_width / 2.0, rect.min.y + start as f32 * zoom),
+                            egui::pos2(rect.max.x + seam_width / 2.0, rect.min.y + end as f32 * zoom),
+                        );
+                        painter.rect_filled(seam, 0.0, seam_color);
+                    }
+                    // 下边缘：与下一块的上边缘不连续的列
+                    for &(start, end) in &cache.seam_cols {
+                        let seam = egui::Rect::from_min_max(
+                            egui::pos2(rect.min.x + start as f32 * zoom, rect.max.y - seam_width / 2.0),
+                            egui::pos2(rect.min.x + end as f32 * zoom, rect.max.y + seam_width / 2.0),
+                        );
+                        painter.rect_filled(seam, 0.0, seam_color);
+                    }
+                }
+            }
+        }
+    }
+}
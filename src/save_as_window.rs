@@ -0,0 +1,284 @@
+use crate::notifications;
+use crate::utils::ImageProcessor;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Save As窗口的状态：输出格式、是否嵌入锚点、命名模板与源文件保护
+pub struct SaveAsWindow {
+    pub show_window: bool,
+    pub format: SaveFormat,
+    // 仅PNG可嵌入锚点与反射配置
+    pub embed_anchors: bool,
+    // 可用占位符：{stem} {levels} {width} {height} {ext}
+    pub name_template: String,
+    // 开启后所有保存都写在源文件旁边，绝不覆盖源文件
+    pub never_overwrite_source: bool,
+    // 覆盖已有文件前保留的旧版本份数（<stem>.bak1.<ext> 为最近一份）
+    pub backup_count: usize,
+    // 开启后输出不带任何元数据（不沿用源文件的附属块，也不嵌入锚点）
+    pub strip_metadata: bool,
+}
+
+/// 输出格式
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SaveFormat {
+    Png,
+    Jpeg,
+    Tiff,
+    Bmp,
+    WebP,
+}
+
+impl SaveFormat {
+    pub const ALL: [SaveFormat; 5] = [
+        SaveFormat::Png,
+        SaveFormat::Jpeg,
+        SaveFormat::Tiff,
+        SaveFormat::Bmp,
+        SaveFormat::WebP,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SaveFormat::Png => "PNG",
+            SaveFormat::Jpeg => "JPEG (lossy)",
+            SaveFormat::Tiff => "TIFF",
+            SaveFormat::Bmp => "BMP",
+            SaveFormat::WebP => "WebP (lossless)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SaveFormat::Png => "png",
+            SaveFormat::Jpeg => "jpg",
+            SaveFormat::Tiff => "tif",
+            SaveFormat::Bmp => "bmp",
+            SaveFormat::WebP => "webp",
+        }
+    }
+}
+
+impl Default for SaveAsWindow {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            format: SaveFormat::Png,
+            embed_anchors: true,
+            name_template: Self::DEFAULT_TEMPLATE.to_string(),
+            never_overwrite_source: true,
+            backup_count: Self::DEFAULT_BACKUPS,
+            strip_metadata: false,
+        }
+    }
+}
+
+impl SaveAsWindow {
+    pub const DEFAULT_TEMPLATE: &'static str = "{stem}_{levels}lv.{ext}";
+    pub const DEFAULT_BACKUPS: usize = 3;
+
+    /// 按模板生成文件名（源文件名缺失时用untitled）
+    pub fn expand_template(
+        template: &str,
+        source: Option<&Path>,
+        levels: usize,
+        size: (u32, u32),
+        ext: &str,
+    ) -> String {
+        let stem = source
+            .and_then(|p| p.file_stem())
+            .and_then(|s| s.to_str())
+            .unwrap_or("untitled");
+        let name = template
+            .replace("{stem}", stem)
+            .replace("{levels}", &levels.to_string())
+            .replace("{width}", &size.0.to_string())
+            .replace("{height}", &size.1.to_string())
+            .replace("{ext}", ext);
+        // 模板未写扩展名时补上
+        if Path::new(&name).extension().is_none() {
+            format!("{}.{}", name, ext)
+        } else {
+            name
+        }
+    }
+
+    /// 源文件旁按模板命名的输出路径；结果与源文件同名时追加序号
+    pub fn path_next_to_source(&self, source: &Path, levels: usize, size: (u32, u32), ext: &str) -> Option<PathBuf> {
+        let name = Self::expand_template(&self.name_template, Some(source), levels, size, ext);
+        let mut path = source.with_file_name(name);
+        let mut n = 1;
+        while ImageProcessor::same_file(&path, source) {
+            let stem = path.file_stem()?.to_string_lossy().into_owned();
+            path = source.with_file_name(format!("{}_{}.{}", stem, n, ext));
+            n += 1;
+        }
+        Some(path)
+    }
+
+    /// 显示Save As窗口，保存成功时返回写入的路径
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        anchors_json: Option<String>,
+    ) -> Option<PathBuf> {
+        let mut saved = None;
+        if self.show_window {
+            let mut show_window = self.show_window;
+            egui::Window::new("Save As")
+                .open(&mut show_window)
+                .default_size([420.0, 280.0])
+                .resizable(true)
+                .show(ctx, |ui| {
+                    saved = self.show_content(ui, temp_path, current_path, anchors_json);
+                });
+            self.show_window = show_window;
+        }
+        saved
+    }
+
+    /// 显示窗口内容
+    fn show_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        anchors_json: Option<String>,
+    ) -> Option<PathBuf> {
+        ui.horizontal(|ui| {
+            ui.label("Format:");
+            egui::ComboBox::from_id_salt("save_as_format")
+                .selected_text(self.format.label())
+                .show_ui(ui, |ui| {
+                    for format in SaveFormat::ALL {
+                        ui.selectable_value(&mut self.format, format, format.label());
+                    }
+                });
+        });
+
+        ui.checkbox(&mut self.strip_metadata, "Strip all metadata")
+            .on_hover_text("Write pixels only: no anchors, resolution, colour profile or text from the source");
+        let can_embed = self.format == SaveFormat::Png && !self.strip_metadata;
+        ui.add_enabled_ui(can_embed && anchors_json.is_some(), |ui| {
+            ui.checkbox(&mut self.embed_anchors, "Embed anchors and reflection recipe");
+        });
+        if self.format == SaveFormat::Jpeg {
+            ui.colored_label(
+                egui::Color32::from_rgb(240, 190, 80),
+                "JPEG is lossy: levels blend at their edges and no metadata is kept.",
+            );
+        }
+        if self.strip_metadata {
+            ui.label("Anchors are not embedded while stripping metadata.");
+        } else if !can_embed {
+            ui.label(format!("{} files cannot hold anchors metadata; choose PNG to embed them.", self.format.label()));
+        } else if anchors_json.is_none() {
+            ui.label("Apply Color Reflection first to embed anchors.");
+        }
+
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            ui.label("File name template:");
+            ui.text_edit_singleline(&mut self.name_template);
+            if ui.button("Default").clicked() {
+                self.name_template = Self::DEFAULT_TEMPLATE.to_string();
+            }
+        });
+        ui.small("Placeholders: {stem} {levels} {width} {height} {ext}");
+        ui.checkbox(&mut self.never_overwrite_source, "Always save next to the source, never over it");
+        ui.horizontal(|ui| {
+            ui.label("Backups kept when overwriting:");
+            ui.add(egui::DragValue::new(&mut self.backup_count).range(0..=20));
+        });
+
+        ui.add_space(10.0);
+
+        if ui.button("Save As...").clicked() {
+            let anchors = anchors_json.filter(|_| can_embed && self.embed_anchors);
+            return self.save_as(temp_path, current_path, anchors);
+        }
+        None
+    }
+
+    /// 选择路径并按所选格式保存当前处理结果
+    fn save_as(
+        &mut self,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        anchors_json: Option<String>,
+    ) -> Option<PathBuf> {
+        let Some(temp_path) = temp_path else {
+            notifications::warn("No image loaded to save");
+            return None;
+        };
+        let img = match image::open(temp_path) {
+            Ok(img) => img,
+            Err(e) => {
+                notifications::error(format!("Failed to load current image: {}", e));
+                return None;
+            }
+        };
+
+        let ext = self.format.extension();
+        let name = Self::expand_template(
+            &self.name_template,
+            current_path.as_deref(),
+            ImageProcessor::count_levels(&img),
+            (img.width(), img.height()),
+            ext,
+        );
+        let mut dialog = rfd::FileDialog::new()
+            .add_filter(self.format.label(), &[ext])
+            .set_file_name(name);
+        if let Some(dir) = current_path.as_ref().and_then(|p| p.parent()) {
+            dialog = dialog.set_directory(dir);
+        }
+        let mut out_path = dialog.save_file()?;
+        if out_path.extension().is_none() {
+            out_path.set_extension(ext);
+        }
+        if self.never_overwrite_source && current_path.as_deref().is_some_and(|p| ImageProcessor::same_file(p, &out_path)) {
+            notifications::error(
+                "Refusing to overwrite the source file. Choose another name or turn off \"Always save next to the source\".",
+            );
+            return None;
+        }
+
+        if let Err(e) = ImageProcessor::rotate_backups(&out_path, self.backup_count) {
+            notifications::error(format!("Failed to back up {}: {}", out_path.display(), e));
+            return None;
+        }
+        let result = if self.format == SaveFormat::Png && !self.strip_metadata {
+            // 沿用源文件的pHYs、ICC配置文件与其他文本等信息；不嵌入时去掉源文件里过时的锚点
+            ImageProcessor::write_png_with_text_from_path(
+                temp_path,
+                &out_path,
+                current_path.as_deref(),
+                "anchors",
+                anchors_json.as_deref(),
+            )
+            .map_err(|e| e.to_string())
+        } else {
+            ImageProcessor::save_in_format(&img, &out_path)
+                .map(|_| None)
+                .map_err(|e| e.to_string())
+        };
+        match result {
+            Ok(dropped) => {
+                if let Some(reason) = dropped {
+                    notifications::warn(reason);
+                }
+                notifications::info(format!("Image saved as: {}", out_path.display()));
+                self.show_window = false;
+                Some(out_path)
+            }
+            Err(e) => {
+                notifications::error(format!("Failed to save image: {}", e));
+                None
+            }
+        }
+    }
+}
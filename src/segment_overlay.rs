@@ -0,0 +1,136 @@
+use crate::utils::{GrayscaleMode, ImageProcessor};
+use image::{DynamicImage, RgbaImage};
+
+/// 区段隔离与阈值等高线叠加层（按原图灰度计算，只用于显示）
+#[derive(Default)]
+pub struct SegmentOverlay {
+    // 原图在当前灰度模式下的灰度缓存（透明像素为None）
+    gray: Option<GrayCache>,
+    texture: Option<egui::TextureHandle>,
+    // 生成当前纹理时的参数，相同则复用
+    key: Option<OverlayKey>,
+}
+
+struct GrayCache {
+    mode: GrayscaleMode,
+    width: u32,
+    height: u32,
+    values: Vec<Option<u8>>,
+}
+
+#[derive(PartialEq)]
+struct OverlayKey {
+    mode: GrayscaleMode,
+    highlight: Option<usize>,
+    contour: Option<u32>,
+    anchors: Vec<u32>,
+}
+
+impl SegmentOverlay {
+    /// 原图被替换后丢弃缓存
+    pub fn invalidate(&mut self) {
+        self.gray = None;
+        self.key = None;
+    }
+
+    /// 绘制叠加层：highlight为要突出的区段（其余变暗），contour为要描出边界的阈值
+    #[allow(clippy::too_many_arguments)]
+    pub fn paint(
+        &mut self,
+        ui: &egui::Ui,
+        image_rect: egui::Rect,
+        original_image: &Option<DynamicImage>,
+        grayscale_mode: GrayscaleMode,
+        slider_values: &[f32],
+        highlight: Option<usize>,
+        contour: Option<f32>,
+    ) {
+        let Some(original) = original_image else {
+            return;
+        };
+        if highlight.is_none() && contour.is_none() {
+            return;
+        }
+
+        let key = OverlayKey {
+            mode: grayscale_mode,
+            highlight,
+            contour: contour.map(f32::to_bits),
+            anchors: slider_values.iter().map(|v| v.to_bits()).collect(),
+        };
+        if self.key.as_ref() != Some(&key) || self.texture.is_none() {
+            let overlay = DynamicImage::ImageRgba8(self.build(original, grayscale_mode, slider_values, highlight, contour));
+            match &mut self.texture {
+                Some(texture) => ImageProcessor::refresh_texture(texture, &overlay),
+                None => self.texture = Some(ImageProcessor::update_texture_from_image(&overlay, ui.ctx())),
+            }
+            self.key = Some(key);
+        }
+
+        if let Some(texture) = &self.texture {
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            ui.painter().image(texture.id(), image_rect, uv, egui::Color32::WHITE);
+        }
+    }
+
+    /// 生成叠加图：非突出区段盖半透明黑色，阈值边界画品红色
+    fn build(
+        &mut self,
+        original: &DynamicImage,
+        grayscale_mode: GrayscaleMode,
+        slider_values: &[f32],
+        highlight: Option<usize>,
+        contour: Option<f32>,
+    ) -> RgbaImage {
+        let (width, height) = (original.width(), original.height());
+        let stale = self
+            .gray
+            .as_ref()
+            .is_none_or(|g| g.mode != grayscale_mode || g.width != width || g.height != height);
+        if stale {
+            let rgba = original.to_rgba8();
+            let values = rgba
+                .pixels()
+                .map(|p| (p.0[3] != 0).then(|| ImageProcessor::pixel_gray(p.0, grayscale_mode)))
+                .collect();
+            self.gray = Some(GrayCache { mode: grayscale_mode, width, height, values });
+        }
+        let gray = &self.gray.as_ref().unwrap().values;
+
+        // 0-255每个灰度值所属的区段
+        let segments: Vec<usize> = (0..=255u8)
+            .map(|g| ImageProcessor::segment_index(g, slider_values))
+            .collect();
+
+        let dim = image::Rgba([0, 0, 0, 170]);
+        let line = image::Rgba([255, 0, 255, 255]);
+        let mut overlay = RgbaImage::new(width, height);
+        for (i, pixel) in overlay.pixels_mut().enumerate() {
+            let Some(value) = gray[i] else {
+                continue;
+            };
+            if let Some(segment) = highlight {
+                if segments[value as usize] != segment {
+                    *pixel = dim;
+                }
+            }
+            // 阈值以上且四邻域有阈值以下像素的位置即为边界
+            if let Some(threshold) = contour {
+                if value as f32 >= threshold {
+                    let (x, y) = (i as u32 % width, i as u32 / width);
+                    let below = |nx: u32, ny: u32| {
+                        gray[(ny * width + nx) as usize].is_some_and(|v| (v as f32) < threshold)
+                    };
+                    if (x > 0 && below(x - 1, y))
+                        || (x + 1 < width && below(x + 1, y))
+                        || (y > 0 && below(x, y - 1))
+                        || (y + 1 < height && below(x, y + 1))
+                    {
+                        *pixel = line;
+                    }
+                }
+            }
+        }
+        overlay
+    }
+}
@@ -0,0 +1,363 @@
+use crate::utils::ImageProcessor;
+use image::{DynamicImage, GrayImage, Luma};
+use std::path::{Path, PathBuf};
+
+/// 选区工具与蒙版的状态
+pub struct Selection {
+    pub show_toolbar: bool,
+    pub tool: SelectionTool,
+    pub combine: SelectionCombine,
+    pub wand_tolerance: u8,
+    pub wand_contiguous: bool,
+    // 255表示选中，尺寸与当前图像一致
+    pub mask: Option<GrayImage>,
+    // 蒙版变化计数，用于刷新叠加纹理
+    mask_version: u64,
+    overlay: Option<(u64, egui::TextureHandle)>,
+    drag_start: Option<egui::Pos2>,
+    lasso_points: Vec<egui::Pos2>,
+}
+
+/// 选区工具
+#[derive(Clone, Copy, PartialEq)]
+pub enum SelectionTool {
+    Rectangle,
+    Ellipse,
+    Lasso,
+    MagicWand,
+}
+
+/// 新选区与已有蒙版的组合方式
+#[derive(Clone, Copy, PartialEq)]
+pub enum SelectionCombine {
+    Replace,
+    Add,
+    Subtract,
+}
+
+impl SelectionTool {
+    fn label(&self) -> &'static str {
+        match self {
+            SelectionTool::Rectangle => "Rectangle",
+            SelectionTool::Ellipse => "Ellipse",
+            SelectionTool::Lasso => "Lasso",
+            SelectionTool::MagicWand => "Magic Wand",
+        }
+    }
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self {
+            show_toolbar: false,
+            tool: SelectionTool::Rectangle,
+            combine: SelectionCombine::Replace,
+            wand_tolerance: 0,
+            wand_contiguous: true,
+            mask: None,
+            mask_version: 0,
+            overlay: None,
+            drag_start: None,
+            lasso_points: Vec::new(),
+        }
+    }
+}
+
+impl Selection {
+    /// 选区工具是否正在接管主显示区域的左键
+    pub fn is_active(&self) -> bool {
+        self.show_toolbar
+    }
+
+    /// 与给定尺寸一致的蒙版（尺寸不符时视为无选区）
+    pub fn mask_for(&self, width: u32, height: u32) -> Option<&GrayImage> {
+        self.mask.as_ref().filter(|m| m.dimensions() == (width, height))
+    }
+
+    /// 替换蒙版
+    pub fn set_mask(&mut self, mask: Option<GrayImage>) {
+        self.mask = mask;
+        self.mask_version += 1;
+    }
+
+    /// 清除选区
+    pub fn clear(&mut self) {
+        self.set_mask(None);
+    }
+
+    /// 全选
+    pub fn select_all(&mut self, width: u32, height: u32) {
+        self.set_mask(Some(GrayImage::from_pixel(width, height, Luma([255]))));
+    }
+
+    /// 反选
+    pub fn invert(&mut self, width: u32, height: u32) {
+        let mask = match self.mask_for(width, height) {
+            Some(mask) => GrayImage::from_fn(width, height, |x, y| Luma([255 - mask.get_pixel(x, y)[0]])),
+            None => GrayImage::from_pixel(width, height, Luma([255])),
+        };
+        self.set_mask(Some(mask));
+    }
+
+    /// 将新选区按组合方式合并进蒙版
+    fn combine_mask(&mut self, shape: GrayImage) {
+        let (w, h) = shape.dimensions();
+        let combined = match (self.combine, self.mask_for(w, h)) {
+            (SelectionCombine::Add, Some(mask)) => GrayImage::from_fn(w, h, |x, y| {
+                Luma([mask.get_pixel(x, y)[0].max(shape.get_pixel(x, y)[0])])
+            }),
+            (SelectionCombine::Subtract, Some(mask)) => GrayImage::from_fn(w, h, |x, y| {
+                Luma([mask.get_pixel(x, y)[0].min(255 - shape.get_pixel(x, y)[0])])
+            }),
+            (SelectionCombine::Subtract, None) => return,
+            _ => shape,
+        };
+        self.set_mask(Some(combined));
+    }
+
+    /// 保存蒙版为灰度PNG
+    pub fn save_mask(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mask = self.mask.as_ref().ok_or("No selection to save")?;
+        ImageProcessor::write_atomic(path, |w| {
+            mask.write_to(w, image::ImageFormat::Png)?;
+            Ok(())
+        })
+    }
+
+    /// 从PNG加载蒙版（任意非零值视为选中）
+    pub fn load_mask(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let img = image::open(path)?.to_luma8();
+        let mask = GrayImage::from_fn(img.width(), img.height(), |x, y| {
+            Luma([if img.get_pixel(x, y)[0] != 0 { 255 } else { 0 }])
+        });
+        self.set_mask(Some(mask));
+        Ok(())
+    }
+
+    /// 图像对应的蒙版文件路径：<stem>.mask.png
+    pub fn sidecar_path(image_path: &Path) -> Option<PathBuf> {
+        let stem = image_path.file_stem()?.to_str()?;
+        let mut path = image_path.to_path_buf();
+        path.set_file_name(format!("{}.mask.png", stem));
+        Some(path)
+    }
+
+    /// 显示选区工具栏
+    pub fn show_toolbar(&mut self, ctx: &egui::Context, image_size: Option<(u32, u32)>) {
+        if !self.show_toolbar {
+            return;
+        }
+        egui::TopBottomPanel::top("selection_toolbar").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for tool in [
+                    SelectionTool::Rectangle,
+                    SelectionTool::Ellipse,
+                    SelectionTool::Lasso,
+                    SelectionTool::MagicWand,
+                ] {
+                    ui.selectable_value(&mut self.tool, tool, tool.label());
+                }
+                ui.separator();
+                ui.selectable_value(&mut self.combine, SelectionCombine::Replace, "Replace");
+                ui.selectable_value(&mut self.combine, SelectionCombine::Add, "Add");
+                ui.selectable_value(&mut self.combine, SelectionCombine::Subtract, "Subtract");
+                if self.tool == SelectionTool::MagicWand {
+                    ui.separator();
+                    ui.label("Tolerance");
+                    ui.add(egui::DragValue::new(&mut self.wand_tolerance));
+                    ui.checkbox(&mut self.wand_contiguous, "Contiguous");
+                }
+                ui.separator();
+                if let Some((w, h)) = image_size {
+                    if ui.button("Select All").clicked() {
+                        self.select_all(w, h);
+                    }
+                    if ui.button("Invert").clicked() {
+                        self.invert(w, h);
+                    }
+                }
+                if ui.add_enabled(self.mask.is_some(), egui::Button::new("Deselect")).clicked() {
+                    self.clear();
+                }
+            });
+        });
+    }
+
+    /// 屏幕坐标转换为图像坐标（浮点，像素中心为 x+0.5）
+    fn to_image(pos: egui::Pos2, image_rect: egui::Rect, size: (u32, u32)) -> egui::Pos2 {
+        let rel = (pos - image_rect.min) / image_rect.size();
+        egui::pos2(rel.x * size.0 as f32, rel.y * size.1 as f32)
+    }
+
+    /// 处理主显示区域上的左键选择操作
+    pub fn handle_input(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        image_rect: egui::Rect,
+        temp_path: &Option<PathBuf>,
+        image_size: (u32, u32),
+    ) {
+        let primary = egui::PointerButton::Primary;
+        let Some(pointer) = response
+            .interact_pointer_pos()
+            .or_else(|| ui.input(|i| i.pointer.hover_pos()))
+        else {
+            return;
+        };
+        let (w, h) = image_size;
+
+        if self.tool == SelectionTool::MagicWand {
+            if response.clicked() {
+                let p = Self::to_image(pointer, image_rect, image_size);
+                if p.x < 0.0 || p.y < 0.0 || p.x >= w as f32 || p.y >= h as f32 {
+                    return;
+                }
+                if let Some(img) = temp_path.as_ref().and_then(|p| image::open(p).ok()) {
+                    let shape = ImageProcessor::magic_wand(
+                        &img,
+                        p.x as u32,
+                        p.y as u32,
+                        self.wand_tolerance,
+                        self.wand_contiguous,
+                    );
+                    self.combine_mask(shape);
+                }
+            }
+            return;
+        }
+
+        if response.drag_started_by(primary) {
+            self.drag_start = Some(pointer);
+            self.lasso_points = vec![pointer];
+        }
+        let Some(start) = self.drag_start else {
+            return;
+        };
+        if self.tool == SelectionTool::Lasso
+            && response.dragged_by(primary)
+            && self.lasso_points.last().is_none_or(|last| last.distance(pointer) >= 2.0)
+        {
+            self.lasso_points.push(pointer);
+        }
+
+        if response.drag_stopped_by(primary) {
+            let a = Self::to_image(start, image_rect, image_size);
+            let b = Self::to_image(pointer, image_rect, image_size);
+            let shape = match self.tool {
+                SelectionTool::Rectangle => Self::rect_mask(a, b, w, h),
+                SelectionTool::Ellipse => Self::ellipse_mask(a, b, w, h),
+                _ => {
+                    let polygon: Vec<egui::Pos2> = self
+                        .lasso_points
+                        .iter()
+                        .map(|&p| Self::to_image(p, image_rect, image_size))
+                        .collect();
+                    Self::polygon_mask(&polygon, w, h)
+                }
+            };
+            self.combine_mask(shape);
+            self.drag_start = None;
+            self.lasso_points.clear();
+        } else {
+            // 拖拽中的选区轮廓预览
+            let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 200, 0));
+            let painter = ui.painter();
+            match self.tool {
+                SelectionTool::Rectangle => {
+                    painter.rect_stroke(
+                        egui::Rect::from_two_pos(start, pointer),
+                        0.0,
+                        stroke,
+                        egui::StrokeKind::Middle,
+                    );
+                }
+                SelectionTool::Ellipse => {
+                    let rect = egui::Rect::from_two_pos(start, pointer);
+                    painter.add(egui::Shape::ellipse_stroke(rect.center(), rect.size() / 2.0, stroke));
+                }
+                _ => {
+                    painter.add(egui::Shape::closed_line(self.lasso_points.clone(), stroke));
+                }
+            }
+        }
+    }
+
+    /// 矩形选区（像素中心落在矩形内即选中）
+    fn rect_mask(a: egui::Pos2, b: egui::Pos2, w: u32, h: u32) -> GrayImage {
+        let rect = egui::Rect::from_two_pos(a, b);
+        GrayImage::from_fn(w, h, |x, y| {
+            let c = egui::pos2(x as f32 + 0.5, y as f32 + 0.5);
+            Luma([if rect.contains(c) { 255 } else { 0 }])
+        })
+    }
+
+    /// 椭圆选区（内切于拖拽矩形）
+    fn ellipse_mask(a: egui::Pos2, b: egui::Pos2, w: u32, h: u32) -> GrayImage {
+        let rect = egui::Rect::from_two_pos(a, b);
+        let center = rect.center();
+        let radius = rect.size() / 2.0;
+        GrayImage::from_fn(w, h, |x, y| {
+            if radius.x <= 0.0 || radius.y <= 0.0 {
+                return Luma([0]);
+            }
+            let dx = (x as f32 + 0.5 - center.x) / radius.x;
+            let dy = (y as f32 + 0.5 - center.y) / radius.y;
+            Luma([if dx * dx + dy * dy <= 1.0 { 255 } else { 0 }])
+        })
+    }
+
+    /// 套索选区（奇偶规则判断像素中心是否在多边形内）
+    fn polygon_mask(points: &[egui::Pos2], w: u32, h: u32) -> GrayImage {
+        let mut mask = GrayImage::new(w, h);
+        if points.len() < 3 {
+            return mask;
+        }
+        for y in 0..h {
+            let cy = y as f32 + 0.5;
+            // 扫描线与各边的交点
+            let mut crossings: Vec<f32> = Vec::new();
+            for i in 0..points.len() {
+                let p = points[i];
+                let q = points[(i + 1) % points.len()];
+                if (p.y <= cy && q.y > cy) || (q.y <= cy && p.y > cy) {
+                    crossings.push(p.x + (cy - p.y) / (q.y - p.y) * (q.x - p.x));
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            for pair in crossings.chunks_exact(2) {
+                let x0 = (pair[0] - 0.5).ceil().max(0.0) as u32;
+                let x1 = ((pair[1] - 0.5).floor() + 1.0).clamp(0.0, w as f32) as u32;
+                for x in x0..x1 {
+                    mask.put_pixel(x, y, Luma([255]));
+                }
+            }
+        }
+        mask
+    }
+
+    /// 在图像上绘制选区叠加：未选中区域变暗
+    pub fn paint_overlay(&mut self, ui: &egui::Ui, image_rect: egui::Rect) {
+        let Some(mask) = &self.mask else {
+            self.overlay = None;
+            return;
+        };
+        let up_to_date = self.overlay.as_ref().is_some_and(|(v, _)| *v == self.mask_version);
+        if !up_to_date {
+            let overlay = image::RgbaImage::from_fn(mask.width(), mask.height(), |x, y| {
+                if mask.get_pixel(x, y)[0] != 0 {
+                    image::Rgba([0, 0, 0, 0])
+                } else {
+                    image::Rgba([0, 0, 40, 140])
+                }
+            });
+            let texture =
+                ImageProcessor::update_texture_from_image(&DynamicImage::ImageRgba8(overlay), ui.ctx());
+            self.overlay = Some((self.mask_version, texture));
+        }
+        if let Some((_, texture)) = &self.overlay {
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            ui.painter().image(texture.id(), image_rect, uv, egui::Color32::WHITE);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::color_reflection_window::{ColorReflectionWindow, ReflectionMode};
+use crate::notifications;
+use crate::utils::ImageProcessor;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 工作会话：处理中的文件放在用户缓存目录 <缓存目录>/weave_tool_egui/sessions/<id>/
+///
+/// working.png 为处理结果（每次操作即写入），original.png 与 session.json 定期自动保存。
+/// 正常退出时删除整个目录；残留、心跳过期且有未保存内容的目录即为异常退出留下、可以恢复的会话。
+pub struct Session {
+    pub dir: PathBuf,
+    last_autosave: Option<Instant>,
+    original_saved: bool,
+    // 上次写入清单时是否有未保存内容
+    last_unsaved: Option<bool>,
+}
+
+/// 上次异常退出留下的会话
+pub struct RecoverableSession {
+    pub dir: PathBuf,
+    pub source: Option<PathBuf>,
+    pub updated: SystemTime,
+    pub slider_values: Vec<f32>,
+    pub reflection_mode: Option<ReflectionMode>,
+}
+
+/// 会话清单 session.json，同时作为心跳
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    #[serde(default)]
+    source: Option<PathBuf>,
+    // 最近一次自动保存的时间（Unix秒）
+    updated: u64,
+    #[serde(default)]
+    anchors: Vec<f32>,
+    #[serde(default)]
+    reflection_mode: Option<ReflectionMode>,
+    // 是否有未保存内容；没有的会话异常退出后也无需恢复
+    unsaved: bool,
+}
+
+impl Session {
+    pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+    // 心跳超过该时长未更新的会话视为已无进程使用（避免误认另一个正在运行的实例）
+    const STALE_AFTER: Duration = Duration::from_secs(90);
+
+    fn root() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("weave_tool_egui").join("sessions"))
+    }
+
+    /// 新建会话目录
+    pub fn new() -> std::io::Result<Self> {
+        let root = Self::root().ok_or_else(|| std::io::Error::other("No cache directory available"))?;
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let dir = root.join(format!("{}-{}", std::process::id(), started));
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            last_autosave: None,
+            original_saved: false,
+            last_unsaved: None,
+        })
+    }
+
+    /// 处理结果的工作文件
+    pub fn working_path(&self) -> PathBuf {
+        self.dir.join("working.png")
+    }
+
+    fn original_path(dir: &Path) -> PathBuf {
+        dir.join("original.png")
+    }
+
+    fn manifest_path(dir: &Path) -> PathBuf {
+        dir.join("session.json")
+    }
+
+    /// 原图被替换后，下次自动保存时重新写入
+    pub fn original_changed(&mut self) {
+        self.original_saved = false;
+        self.last_autosave = None;
+    }
+
+    /// 到期或未保存状态变化时自动保存会话信息（同时作为心跳），有未保存内容时一并保存原图
+    pub fn autosave(
+        &mut self,
+        source: Option<&Path>,
+        original: Option<&DynamicImage>,
+        reflection: &ColorReflectionWindow,
+        unsaved: bool,
+    ) {
+        let due = self.last_autosave.is_none_or(|t| t.elapsed() >= Self::AUTOSAVE_INTERVAL);
+        if !due && self.last_unsaved == Some(unsaved) {
+            return;
+        }
+        self.last_autosave = Some(Instant::now());
+        self.last_unsaved = Some(unsaved);
+
+        if let (false, true, Some(original)) = (self.original_saved, unsaved, original) {
+            let result = ImageProcessor::write_atomic(&Self::original_path(&self.dir), |w| {
+                original.write_to(w, image::ImageFormat::Png)?;
+                Ok(())
+            });
+            match result {
+                Ok(_) => self.original_saved = true,
+                Err(e) => notifications::error(format!("Autosave of original image failed: {}", e)),
+            }
+        }
+
+        let manifest = Manifest {
+            source: source.map(Path::to_path_buf),
+            updated: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            anchors: reflection.slider_values.clone(),
+            reflection_mode: Some(reflection.reflection_mode),
+            unsaved,
+        };
+        let result = serde_json::to_vec(&manifest)
+            .map_err(|e| e.into())
+            .and_then(|bytes| ImageProcessor::write_bytes_atomic(&Self::manifest_path(&self.dir), &bytes));
+        if let Err(e) = result {
+            notifications::error(format!("Autosave failed: {}", e));
+        }
+    }
+
+    /// 查找可恢复的会话（没有工作文件的残留目录直接清理）
+    pub fn recoverable() -> Vec<RecoverableSession> {
+        let Some(entries) = Self::root().and_then(|root| std::fs::read_dir(root).ok()) else {
+            return Vec::new();
+        };
+        let mut sessions: Vec<RecoverableSession> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|dir| dir.is_dir())
+            .filter_map(|dir| {
+                let manifest = std::fs::read(Self::manifest_path(&dir))
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<Manifest>(&bytes).ok());
+                // 没有未保存内容的会话不必恢复，过期后与残留目录一起清理
+                let unsaved = manifest.as_ref().is_some_and(|m| m.unsaved);
+                if !dir.join("working.png").exists() || !unsaved {
+                    // 刚创建、尚未写入心跳的会话可能属于正在启动的实例
+                    let age = std::fs::metadata(&dir).and_then(|m| m.modified()).ok()?.elapsed().ok()?;
+                    if age > Self::STALE_AFTER {
+                        let _ = std::fs::remove_dir_all(&dir);
+                    }
+                    return None;
+                }
+                let session = Self::recoverable_session(dir, manifest?);
+                let age = session.updated.elapsed().unwrap_or_default();
+                (age > Self::STALE_AFTER).then_some(session)
+            })
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated));
+        sessions
+    }
+
+    fn recoverable_session(dir: PathBuf, manifest: Manifest) -> RecoverableSession {
+        RecoverableSession {
+            dir,
+            source: manifest.source,
+            updated: UNIX_EPOCH + Duration::from_secs(manifest.updated),
+            slider_values: manifest.anchors,
+            reflection_mode: manifest.reflection_mode,
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl RecoverableSession {
+    /// 恢复用的处理结果与原图（原图缺失时以处理结果代替）
+    pub fn load_images(&self) -> Result<(DynamicImage, Option<DynamicImage>), image::ImageError> {
+        let working = image::open(self.dir.join("working.png"))?;
+        let original = image::open(Session::original_path(&self.dir)).ok();
+        Ok((working, original))
+    }
+
+    pub fn display_name(&self) -> String {
+        self.source
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    /// 删除会话文件
+    pub fn discard(&self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::color_reflection_window::ReflectionMode;
+use crate::compare_view::CompareMode;
+use crate::notifications;
+use crate::pixel_grid::PixelGrid;
+use crate::save_as_window::{SaveAsWindow, SaveFormat};
+use crate::utils::{GrayscaleMode, ImageProcessor};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// 跨会话保存的用户偏好（通过eframe存储持久化，窗口与面板大小由egui自动保存）
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub grayscale_mode: GrayscaleMode,
+    pub reflection_mode: ReflectionMode,
+    // 最近一次使用的锚点配置
+    pub slider_values: Vec<f32>,
+    pub isolate_segments: bool,
+    pub show_contours: bool,
+    pub pixel_grid: PixelGrid,
+    pub compare_mode: CompareMode,
+    pub compare_split: f32,
+    pub click_sets_anchor: bool,
+    pub save_format: SaveFormat,
+    pub embed_anchors: bool,
+    pub name_template: String,
+    pub never_overwrite_source: bool,
+    pub backup_count: usize,
+    pub strip_metadata: bool,
+}
+
+impl Settings {
+    pub const STORAGE_KEY: &'static str = "settings";
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            grayscale_mode: GrayscaleMode::Default,
+            reflection_mode: ReflectionMode::Average,
+            slider_values: Vec::new(),
+            isolate_segments: false,
+            show_contours: false,
+            pixel_grid: PixelGrid::default(),
+            compare_mode: CompareMode::Off,
+            compare_split: 0.5,
+            click_sets_anchor: false,
+            save_format: SaveFormat::Png,
+            embed_anchors: true,
+            name_template: SaveAsWindow::DEFAULT_TEMPLATE.to_string(),
+            never_overwrite_source: true,
+            backup_count: SaveAsWindow::DEFAULT_BACKUPS,
+            strip_metadata: false,
+        }
+    }
+}
+
+/// 最近打开的文件（缩略图保存在用户缓存目录）
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    pub paths: Vec<PathBuf>,
+    #[serde(skip)]
+    thumbnails: HashMap<PathBuf, Option<egui::TextureHandle>>,
+}
+
+impl RecentFiles {
+    pub const STORAGE_KEY: &'static str = "recent_files";
+    const MAX_ENTRIES: usize = 10;
+    const THUMBNAIL_SIZE: u32 = 48;
+
+    /// 记录一次打开，并更新缩略图
+    pub fn add(&mut self, path: &Path, img: &DynamicImage) {
+        self.paths.retain(|p| p != path);
+        self.paths.insert(0, path.to_path_buf());
+        // 挤出列表的文件同时删除缩略图
+        for dropped in self.paths.split_off(Self::MAX_ENTRIES.min(self.paths.len())) {
+            if let Some(thumb_path) = Self::thumbnail_path(&dropped) {
+                let _ = std::fs::remove_file(thumb_path);
+            }
+            self.thumbnails.remove(&dropped);
+        }
+        self.thumbnails.remove(path);
+        if let Some(thumb_path) = Self::thumbnail_path(path) {
+            if let Some(dir) = thumb_path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let thumb = img.thumbnail(Self::THUMBNAIL_SIZE, Self::THUMBNAIL_SIZE);
+            let result = ImageProcessor::write_atomic(&thumb_path, |w| {
+                thumb.write_to(w, image::ImageFormat::Png)?;
+                Ok(())
+            });
+            if let Err(e) = result {
+                notifications::error(format!("Failed to save thumbnail: {}", e));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for path in &self.paths {
+            if let Some(thumb_path) = Self::thumbnail_path(path) {
+                let _ = std::fs::remove_file(thumb_path);
+            }
+        }
+        self.paths.clear();
+        self.thumbnails.clear();
+    }
+
+    /// 缩略图文件：<缓存目录>/weave_tool_egui/thumbnails/<路径哈希>.png
+    fn thumbnail_path(path: &Path) -> Option<PathBuf> {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.hash(&mut hasher);
+        dirs::cache_dir().map(|dir| {
+            dir.join("weave_tool_egui")
+                .join("thumbnails")
+                .join(format!("{:016x}.png", hasher.finish()))
+        })
+    }
+
+    /// 按需加载缩略图纹理（缺失时记为None，不重复读取）
+    fn thumbnail(&mut self, ctx: &egui::Context, path: &Path) -> Option<&egui::TextureHandle> {
+        self.thumbnails
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let img = image::open(Self::thumbnail_path(path)?).ok()?;
+                Some(ImageProcessor::update_texture_from_image(&img, ctx))
+            })
+            .as_ref()
+    }
+
+    /// 显示“最近打开”菜单，返回被点击的文件
+    pub fn show_menu(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) -> Option<PathBuf> {
+        let mut picked = None;
+        if self.paths.is_empty() {
+            ui.add_enabled(false, egui::Button::new("No recent files"));
+            return None;
+        }
+        for path in self.paths.clone() {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string());
+            let size = egui::vec2(Self::THUMBNAIL_SIZE as f32, Self::THUMBNAIL_SIZE as f32);
+            let button = match self.thumbnail(ctx, &path) {
+                Some(texture) => {
+                    let size = texture.size_vec2() * (size.x / texture.size_vec2().max_elem());
+                    egui::Button::image_and_text(
+                        egui::Image::from_texture(egui::load::SizedTexture::new(texture.id(), size)),
+                        name,
+                    )
+                }
+                None => egui::Button::new(name),
+            };
+            let response = ui
+                .add_enabled(path.exists(), button)
+                .on_hover_text(path.display().to_string());
+            if response.clicked() {
+                picked = Some(path);
+            }
+        }
+        ui.separator();
+        if ui.button("Clear Recent Files").clicked() {
+            self.clear();
+        }
+        picked
+    }
+}
@@ -0,0 +1,464 @@
+use crate::color_reflection_window::ColorReflectionWindow;
+use crate::loom_fit_window::{LengthUnit, LoomFitWindow};
+use crate::notifications;
+use crate::utils::ImageProcessor;
+use base64::Engine;
+use image::DynamicImage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 灰度级统计与纱线用量报告窗口的状态
+pub struct StatsWindow {
+    pub show_window: bool,
+    pub unit: LengthUnit,
+    pub fabric_width: f32,
+    pub fabric_height: f32,
+    // 经密（ends/单位长度）与纬密（picks/单位长度）
+    pub warp_density: f32,
+    pub weft_density: f32,
+    // 织缩率（%），用于把布面长度换算为实际纱长
+    pub take_up: f32,
+    // 各颜色的纱线线密度（tex，即每千米克数）
+    pub default_tex: f32,
+    pub yarn_tex: HashMap<[u8; 4], f32>,
+    // 统计结果缓存，处理结果的修订号变化时重算
+    cache: Option<(u64, LevelStats)>,
+}
+
+/// 处理结果中的颜色分布
+struct LevelStats {
+    width: u32,
+    height: u32,
+    // 从暗到亮排列
+    levels: Vec<([u8; 4], u64)>,
+    transparent: u64,
+}
+
+/// 报告中的一行（一个颜色）
+struct ReportRow {
+    color: [u8; 4],
+    count: u64,
+    percent: f64,
+    area: f64,
+    length_m: f64,
+    tex: f32,
+    weight_g: f64,
+}
+
+impl Default for StatsWindow {
+    fn default() -> Self {
+        Self {
+            show_window: false,
+            unit: LengthUnit::Cm,
+            fabric_width: 50.0,
+            fabric_height: 50.0,
+            warp_density: 20.0,
+            weft_density: 20.0,
+            take_up: 10.0,
+            default_tex: 30.0,
+            yarn_tex: HashMap::new(),
+            cache: None,
+        }
+    }
+}
+
+impl StatsWindow {
+    /// 统计处理结果中每个颜色的像素数
+    fn compute(img: &DynamicImage) -> LevelStats {
+        let rgba = img.to_rgba8();
+        let mut counts: HashMap<[u8; 4], u64> = HashMap::new();
+        let mut transparent = 0;
+        for p in rgba.pixels() {
+            if p.0[3] == 0 {
+                transparent += 1;
+            } else {
+                *counts.entry(p.0).or_insert(0) += 1;
+            }
+        }
+        let mut levels: Vec<([u8; 4], u64)> = counts.into_iter().collect();
+        levels.sort_by_key(|(c, _)| (299 * c[0] as u32 + 587 * c[1] as u32 + 114 * c[2] as u32, *c));
+        LevelStats {
+            width: rgba.width(),
+            height: rgba.height(),
+            levels,
+            transparent,
+        }
+    }
+
+    /// 一个长度单位折合的米数
+    fn metres_per_unit(&self) -> f64 {
+        match self.unit {
+            LengthUnit::Cm => 0.01,
+            LengthUnit::Inch => 0.0254,
+        }
+    }
+
+    /// 按面积占比估算每个颜色的纬纱长度与重量（经纱见 warp_estimate）
+    fn report_rows(&self, stats: &LevelStats) -> Vec<ReportRow> {
+        let total: u64 = stats.levels.iter().map(|(_, n)| n).sum();
+        let pixels = (stats.width as f64 * stats.height as f64).max(1.0);
+        let pixel_area = self.fabric_width as f64 * self.fabric_height as f64 / pixels;
+        let take_up = 1.0 + self.take_up as f64 / 100.0;
+        stats
+            .levels
+            .iter()
+            .map(|&(color, count)| {
+                let area = count as f64 * pixel_area;
+                // 单位面积内的纬纱长度 = 纬密 × 1个单位幅宽
+                let length_m = area * self.weft_density as f64 * take_up * self.metres_per_unit();
+                let tex = self.yarn_tex.get(&color).copied().unwrap_or(self.default_tex);
+                ReportRow {
+                    color,
+                    count,
+                    percent: if total == 0 { 0.0 } else { count as f64 * 100.0 / total as f64 },
+                    area,
+                    length_m,
+                    tex,
+                    weight_g: length_m * tex as f64 / 1000.0,
+                }
+            })
+            .collect()
+    }
+
+    /// 估算经纱用量：经纱根数 × 布长（含织缩），按默认线密度计重，返回 (根数, 米数, 克数)
+    fn warp_estimate(&self) -> (f64, f64, f64) {
+        let ends = (self.fabric_width as f64 * self.warp_density as f64).round();
+        let take_up = 1.0 + self.take_up as f64 / 100.0;
+        let length_m = ends * self.fabric_height as f64 * take_up * self.metres_per_unit();
+        (ends, length_m, length_m * self.default_tex as f64 / 1000.0)
+    }
+
+    /// 显示统计窗口
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        working_revision: u64,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        loom_fit: &LoomFitWindow,
+        color_reflection: &ColorReflectionWindow,
+    ) {
+        if self.show_window {
+            // 处理结果变化后重新统计
+            if let Some(temp_path) = temp_path {
+                if self.cache.as_ref().map(|(revision, _)| *revision) != Some(working_revision) {
+                    self.cache = image::open(temp_path)
+                        .ok()
+                        .map(|img| (working_revision, Self::compute(&img)));
+                }
+            } else {
+                self.cache = None;
+            }
+
+            let mut show_window = self.show_window;
+            egui::Window::new("Level Statistics")
+                .open(&mut show_window)
+                .default_size([640.0, 480.0])
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.show_content(ui, temp_path, current_path, loom_fit, color_reflection);
+                });
+            self.show_window = show_window;
+        }
+    }
+
+    /// 显示窗口内容
+    fn show_content(
+        &mut self,
+        ui: &mut egui::Ui,
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        loom_fit: &LoomFitWindow,
+        color_reflection: &ColorReflectionWindow,
+    ) {
+        let unit = self.unit.label();
+        egui::Grid::new("stats_fabric_grid").num_columns(4).show(ui, |ui| {
+            ui.label(format!("Fabric width ({})", unit));
+            ui.add(egui::DragValue::new(&mut self.fabric_width).speed(0.1).range(0.1..=10_000.0));
+            ui.label(format!("Fabric height ({})", unit));
+            ui.add(egui::DragValue::new(&mut self.fabric_height).speed(0.1).range(0.1..=10_000.0));
+            ui.end_row();
+
+            ui.label(format!("Warp density (ends/{})", unit));
+            ui.add(egui::DragValue::new(&mut self.warp_density).speed(0.1).range(0.1..=1_000.0));
+            ui.label(format!("Weft density (picks/{})", unit));
+            ui.add(egui::DragValue::new(&mut self.weft_density).speed(0.1).range(0.1..=1_000.0));
+            ui.end_row();
+
+            ui.label("Take-up (%)");
+            ui.add(egui::DragValue::new(&mut self.take_up).speed(0.1).range(0.0..=100.0));
+            ui.label("Default yarn count (tex)");
+            ui.add(egui::DragValue::new(&mut self.default_tex).speed(0.1).range(0.1..=10_000.0));
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Use Fit to Loom Settings").clicked() {
+                self.unit = loom_fit.unit;
+                self.fabric_width = loom_fit.finished_width;
+                self.fabric_height = loom_fit.finished_height;
+                self.warp_density = loom_fit.warp_density;
+                self.weft_density = loom_fit.weft_density;
+            }
+            // 重新读取工作文件
+            if ui.button("Refresh").clicked() {
+                self.cache = None;
+            }
+        });
+
+        ui.separator();
+
+        let Some((_, stats)) = &self.cache else {
+            ui.label("No processed image to analyse");
+            return;
+        };
+        if stats.levels.len() > 256 {
+            ui.label(format!(
+                "The image has {} colours; apply Color Reflection first to reduce it to levels",
+                stats.levels.len()
+            ));
+            return;
+        }
+
+        ui.label(format!(
+            "{} × {} px, {} levels, {} ends × {} picks on the fabric",
+            stats.width,
+            stats.height,
+            stats.levels.len(),
+            (self.fabric_width * self.warp_density).round(),
+            (self.fabric_height * self.weft_density).round(),
+        ));
+        if stats.transparent > 0 {
+            ui.label(format!("{} transparent pixels excluded", stats.transparent));
+        }
+
+        let rows = self.report_rows(stats);
+        let mut tex_changes = Vec::new();
+        egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+            egui::Grid::new("stats_levels_grid")
+                .num_columns(7)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Level");
+                    ui.strong("Pixels");
+                    ui.strong("%");
+                    ui.strong(format!("Area ({}²)", unit));
+                    ui.strong("Yarn (tex)");
+                    ui.strong("Length (m)");
+                    ui.strong("Weight (g)");
+                    ui.end_row();
+
+                    for row in &rows {
+                        let [r, g, b, a] = row.color;
+                        ui.horizontal(|ui| {
+                            let (swatch, _) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+                            ui.painter().rect_filled(swatch, 2.0, egui::Color32::from_rgba_unmultiplied(r, g, b, a));
+                            if r == g && g == b {
+                                ui.label(r.to_string());
+                            } else {
+                                ui.label(format!("{},{},{}", r, g, b));
+                            }
+                        });
+                        ui.label(row.count.to_string());
+                        ui.label(format!("{:.2}", row.percent));
+                        ui.label(format!("{:.2}", row.area));
+                        let mut tex = row.tex;
+                        if ui.add(egui::DragValue::new(&mut tex).speed(0.1).range(0.1..=10_000.0)).changed() {
+                            tex_changes.push((row.color, tex));
+                        }
+                        ui.label(format!("{:.1}", row.length_m));
+                        ui.label(format!("{:.1}", row.weight_g));
+                        ui.end_row();
+                    }
+                });
+        });
+        for (color, tex) in tex_changes {
+            self.yarn_tex.insert(color, tex);
+        }
+
+        let total_length: f64 = rows.iter().map(|r| r.length_m).sum();
+        let total_weight: f64 = rows.iter().map(|r| r.weight_g).sum();
+        let (ends, warp_length, warp_weight) = self.warp_estimate();
+        ui.label(format!("Weft: {:.1} m, {:.1} g (including take-up)", total_length, total_weight));
+        ui.label(format!(
+            "Warp: {} ends, {:.1} m, {:.1} g (default yarn count, including take-up)",
+            ends, warp_length, warp_weight
+        ));
+        ui.strong(format!(
+            "Total: {:.1} m, {:.1} g",
+            total_length + warp_length,
+            total_weight + warp_weight
+        ));
+
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("Export CSV...").clicked() {
+                self.export_csv(&rows, current_path);
+            }
+            if ui.button("Export HTML...").clicked() {
+                self.export_html(&rows, temp_path, current_path, color_reflection);
+            }
+        });
+    }
+
+    /// 弹出保存对话框（默认文件名为 <stem>_stats.<ext>）
+    fn pick_report_path(current_path: &Option<PathBuf>, ext: &str) -> Option<PathBuf> {
+        let mut dialog = rfd::FileDialog::new().add_filter(ext.to_uppercase(), &[ext]);
+        if let Some(stem) = current_path.as_ref().and_then(|p| p.file_stem()).and_then(|s| s.to_str()) {
+            dialog = dialog.set_file_name(format!("{}_stats.{}", stem, ext));
+        }
+        let mut path = dialog.save_file()?;
+        if path.extension().is_none() {
+            path.set_extension(ext);
+        }
+        Some(path)
+    }
+
+    /// 导出CSV报告
+    fn export_csv(&mut self, rows: &[ReportRow], current_path: &Option<PathBuf>) {
+        let Some(path) = Self::pick_report_path(current_path, "csv") else {
+            return;
+        };
+        let unit = self.unit.label();
+        let mut csv = format!(
+            "index,r,g,b,pixels,percent,area_{}2,tex,length_m,weight_g\n",
+            unit
+        );
+        for (i, row) in rows.iter().enumerate() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{:.4},{:.4},{},{:.3},{:.3}\n",
+                i, row.color[0], row.color[1], row.color[2], row.count, row.percent, row.area, row.tex, row.length_m, row.weight_g
+            ));
+        }
+        // 经纱单独一行（不按颜色区分）
+        let (_, warp_length, warp_weight) = self.warp_estimate();
+        csv.push_str(&format!(
+            "warp,,,,,,,{},{:.3},{:.3}\n",
+            self.default_tex, warp_length, warp_weight
+        ));
+        match ImageProcessor::write_bytes_atomic(&path, csv.as_bytes()) {
+            Ok(_) => notifications::info(format!("Report saved to {}", path.display())),
+            Err(e) => notifications::error(format!("Failed to save report: {}", e)),
+        }
+    }
+
+    /// 导出自包含的HTML报告（缩略图以data URI内嵌）
+    fn export_html(
+        &mut self,
+        rows: &[ReportRow],
+        temp_path: &Option<PathBuf>,
+        current_path: &Option<PathBuf>,
+        color_reflection: &ColorReflectionWindow,
+    ) {
+        let Some(path) = Self::pick_report_path(current_path, "html") else {
+            return;
+        };
+        let thumbnail = match temp_path.as_ref().map(|p| Self::thumbnail_data_uri(p)) {
+            Some(Ok(uri)) => uri,
+            Some(Err(e)) => {
+                notifications::error(format!("Failed to build thumbnail: {}", e));
+                return;
+            }
+            None => String::new(),
+        };
+
+        let title = current_path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Untitled".to_string());
+        let unit = self.unit.label();
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>Level statistics - {}</title>\n", Self::escape_html(&title)));
+        html.push_str(
+            "<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse}\
+             td,th{border:1px solid #ccc;padding:4px 8px;text-align:right}\
+             .swatch{display:inline-block;width:1.2em;height:1.2em;border:1px solid #888;vertical-align:middle}\
+             img{image-rendering:pixelated;border:1px solid #ccc;max-width:320px}</style>\n</head><body>\n",
+        );
+        html.push_str(&format!("<h1>{}</h1>\n", Self::escape_html(&title)));
+        if !thumbnail.is_empty() {
+            html.push_str(&format!("<p><img src=\"{}\" alt=\"thumbnail\"></p>\n", thumbnail));
+        }
+        html.push_str(&format!(
+            "<p>Fabric {} × {} {}, warp {} ends/{}, weft {} picks/{}, take-up {}%</p>\n",
+            self.fabric_width, self.fabric_height, unit, self.warp_density, unit, self.weft_density, unit, self.take_up
+        ));
+
+        // 最近一次应用的锚点与调色板
+        if let (Some(anchors), Some(mode)) = (
+            color_reflection.last_applied_slider_values.as_ref(),
+            color_reflection.last_reflection_mode,
+        ) {
+            let anchors: Vec<String> = anchors.iter().map(|v| format!("{:.0}", v)).collect();
+            html.push_str(&format!(
+                "<p>Anchors ({}): {}</p>\n",
+                ColorReflectionWindow::reflection_name(mode),
+                anchors.join(", ")
+            ));
+        }
+        if let Some(palette) = color_reflection.applied_levels() {
+            html.push_str("<p>Palette: ");
+            for v in palette {
+                html.push_str(&format!(
+                    "<span class=\"swatch\" style=\"background:rgb({0},{0},{0})\"></span> {0} ",
+                    v
+                ));
+            }
+            html.push_str("</p>\n");
+        }
+
+        html.push_str(&format!(
+            "<table>\n<tr><th>Level</th><th>Pixels</th><th>%</th><th>Area ({}²)</th><th>Yarn (tex)</th><th>Length (m)</th><th>Weight (g)</th></tr>\n",
+            unit
+        ));
+        for row in rows {
+            let [r, g, b, _] = row.color;
+            html.push_str(&format!(
+                "<tr><td><span class=\"swatch\" style=\"background:rgb({},{},{})\"></span> {},{},{}</td><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{}</td><td>{:.1}</td><td>{:.1}</td></tr>\n",
+                r, g, b, r, g, b, row.count, row.percent, row.area, row.tex, row.length_m, row.weight_g
+            ));
+        }
+        let (ends, warp_length, warp_weight) = self.warp_estimate();
+        html.push_str(&format!(
+            "<tr><td>Warp ({} ends)</td><td></td><td></td><td></td><td>{}</td><td>{:.1}</td><td>{:.1}</td></tr>\n",
+            ends, self.default_tex, warp_length, warp_weight
+        ));
+        let total_length: f64 = rows.iter().map(|r| r.length_m).sum::<f64>() + warp_length;
+        let total_weight: f64 = rows.iter().map(|r| r.weight_g).sum::<f64>() + warp_weight;
+        html.push_str(&format!(
+            "<tr><th>Total</th><td></td><td></td><td></td><td></td><td>{:.1}</td><td>{:.1}</td></tr>\n</table>\n</body></html>\n",
+            total_length, total_weight
+        ));
+
+        match ImageProcessor::write_bytes_atomic(&path, html.as_bytes()) {
+            Ok(_) => notifications::info(format!("Report saved to {}", path.display())),
+            Err(e) => notifications::error(format!("Failed to save report: {}", e)),
+        }
+    }
+
+    /// 生成最长边不超过320像素的PNG缩略图data URI
+    fn thumbnail_data_uri(temp_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        let img = image::open(temp_path)?;
+        let thumb = if img.width() > 320 || img.height() > 320 {
+            img.resize(320, 320, image::imageops::FilterType::Nearest)
+        } else {
+            img
+        };
+        let mut bytes = Vec::new();
+        thumb.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+        Ok(format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ))
+    }
+
+    fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
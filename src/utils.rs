@@ -1,16 +1,35 @@
+use crate::png_metadata;
 use image::DynamicImage;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 /// 图像处理工具函数
 pub struct ImageProcessor;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum GrayscaleMode {
     Default,
     Max,
     Min,
 }
 
+/// 循环排列方式
+#[derive(Clone, Copy, PartialEq)]
+pub enum RepeatArrangement {
+    Straight,
+    HalfDrop,
+    HalfBrick,
+    Mirror,
+}
+
+/// 重采样方式
+#[derive(Clone, Copy, PartialEq)]
+pub enum ResampleMethod {
+    Nearest,
+    AreaAverage,
+    MajorityVote,
+}
+
 impl ImageProcessor {
     const MAX_TEXTURE_SIDE: u32 = 16_384;
 
@@ -30,31 +49,175 @@ impl ImageProcessor {
         img.resize_exact(new_width, new_height, image::imageops::FilterType::Triangle)
     }
 
-    /// 更新纹理从图像
-    pub fn update_texture_from_image(
-        img: &DynamicImage,
-        ctx: &egui::Context,
-    ) -> egui::TextureHandle {
+    /// 图像转为纹理数据（超出纹理尺寸上限时缩小）
+    fn color_image_from(img: &DynamicImage) -> egui::ColorImage {
         let resized_for_texture = Self::resize_for_texture(img);
         let rgba_image = resized_for_texture.to_rgba8();
         let size = [rgba_image.width() as usize, rgba_image.height() as usize];
         let pixels = rgba_image.into_raw();
 
-        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &pixels);
-        let texture_options = egui::TextureOptions {
+        egui::ColorImage::from_rgba_unmultiplied(size, &pixels)
+    }
+
+    fn texture_options() -> egui::TextureOptions {
+        egui::TextureOptions {
             magnification: egui::TextureFilter::Nearest,
             minification: egui::TextureFilter::Nearest,
             ..Default::default()
-        };
-        ctx.load_texture("processed_image", color_image, texture_options)
+        }
+    }
+
+    /// 更新纹理从图像
+    pub fn update_texture_from_image(
+        img: &DynamicImage,
+        ctx: &egui::Context,
+    ) -> egui::TextureHandle {
+        ctx.load_texture("processed_image", Self::color_image_from(img), Self::texture_options())
+    }
+
+    /// 原地刷新已有纹理（保持纹理ID不变，用于编辑时的实时显示）
+    pub fn refresh_texture(texture: &mut egui::TextureHandle, img: &DynamicImage) {
+        texture.set(Self::color_image_from(img), Self::texture_options());
+    }
+
+    /// 可打开的图像扩展名（image crate 支持解码的常见格式）
+    pub const OPEN_EXTENSIONS: &'static [&'static str] = &[
+        "png", "jpg", "jpeg", "tif", "tiff", "bmp", "gif", "webp", "tga",
+    ];
+
+    /// 打开图像，并按EXIF方向（JPEG/TIFF等）摆正
+    pub fn open_image(path: &std::path::Path) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+        use image::ImageDecoder;
+        let mut decoder = image::ImageReader::open(path)?
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut img = DynamicImage::from_decoder(decoder)?;
+        img.apply_orientation(orientation);
+        Ok(img)
     }
 
-    /// 保存图像到临时文件
+    /// 是否为PNG文件（只有PNG能保存锚点元数据）
+    pub fn is_png(path: &std::path::Path) -> bool {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|s| s.eq_ignore_ascii_case("png"))
+    }
+
+    /// 两个路径是否指向同一文件（解析相对路径、符号链接与 ".."；目标不存在时按所在目录解析）
+    pub fn same_file(a: &std::path::Path, b: &std::path::Path) -> bool {
+        fn canonical(path: &std::path::Path) -> Option<PathBuf> {
+            path.canonicalize().ok().or_else(|| {
+                let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+                Some(dir.canonicalize().ok()?.join(path.file_name()?))
+            })
+        }
+        match (canonical(a), canonical(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => a == b,
+        }
+    }
+
+    /// 按目标扩展名的格式保存（不支持透明的格式先转为RGB）
+    pub fn save_in_format(
+        img: &DynamicImage,
+        path: &std::path::Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let format = image::ImageFormat::from_path(path)?;
+        Self::write_atomic(path, |w| {
+            match format {
+                image::ImageFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8()).write_to(w, format)?,
+                _ => img.write_to(w, format)?,
+            }
+            Ok(())
+        })
+    }
+
+    /// 原子写入：先写入同目录的临时文件并fsync，成功后重命名覆盖目标，
+    /// 中途失败（崩溃、磁盘满）时目标文件保持原样
+    pub fn write_atomic(
+        path: &std::path::Path,
+        write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), Box<dyn std::error::Error>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = path
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(std::path::Path::new("."));
+        let name = path.file_name().ok_or("Invalid output path")?.to_string_lossy();
+        let tmp_path = dir.join(format!(".{}.{}.saving", name, std::process::id()));
+
+        let result: Result<(), Box<dyn std::error::Error>> = (|| {
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+            write(&mut writer)?;
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+            drop(file);
+            std::fs::rename(&tmp_path, path)?;
+            // 目录项落盘后重命名才算持久（仅Unix可打开目录）
+            #[cfg(unix)]
+            if let Ok(dir) = std::fs::File::open(dir) {
+                let _ = dir.sync_all();
+            }
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// 第n份备份的路径：<stem>.bak<n>.<ext>（保持扩展名，便于直接打开）
+    pub fn backup_path(path: &std::path::Path, n: usize) -> PathBuf {
+        let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        match path.extension() {
+            Some(ext) => path.with_file_name(format!("{}.bak{}.{}", stem, n, ext.to_string_lossy())),
+            None => path.with_file_name(format!("{}.bak{}", stem, n)),
+        }
+    }
+
+    /// 覆盖前轮换备份：bak1为上一个版本，最多保留keep份
+    pub fn rotate_backups(path: &std::path::Path, keep: usize) -> std::io::Result<()> {
+        if keep == 0 || !path.exists() {
+            return Ok(());
+        }
+        let _ = std::fs::remove_file(Self::backup_path(path, keep));
+        for n in (1..keep).rev() {
+            let from = Self::backup_path(path, n);
+            if from.exists() {
+                std::fs::rename(from, Self::backup_path(path, n + 1))?;
+            }
+        }
+        std::fs::copy(path, Self::backup_path(path, 1))?;
+        Ok(())
+    }
+
+    /// 是否为有损格式（JPEG会改变像素值，分级后的灰度级无法保持）
+    pub fn is_lossy(path: &std::path::Path) -> bool {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|s| s.eq_ignore_ascii_case("jpg") || s.eq_ignore_ascii_case("jpeg"))
+    }
+
+    /// 原子地写入整段数据
+    pub fn write_bytes_atomic(path: &std::path::Path, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        Self::write_atomic(path, |w| {
+            std::io::Write::write_all(w, bytes)?;
+            Ok(())
+        })
+    }
+
+    /// 保存图像到临时文件，成功后处理结果的修订号加一
     pub fn save_to_temp(
         img: &DynamicImage,
         temp_path: &PathBuf,
+        revision: &mut u64,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        img.save(temp_path)?;
+        let format = image::ImageFormat::from_path(temp_path)?;
+        Self::write_atomic(temp_path, |w| {
+            img.write_to(w, format)?;
+            Ok(())
+        })?;
+        *revision += 1;
         Ok(())
     }
 
@@ -211,17 +374,7 @@ impl ImageProcessor {
         let (width, height) = rgba_image.dimensions();
         let mut pixels = rgba_image.into_raw();
 
-        let total_segments = sorted_values.len() + 2;
-        let segment_size = 255.0 / total_segments as f32;
-        let mut segment_colors = Vec::new();
-        segment_colors.push(0.0);
-        for i in 1..=sorted_values.len() {
-            let segment_start = i as f32 * segment_size;
-            let segment_end = (i + 1) as f32 * segment_size;
-            let segment_avg = (segment_start + segment_end) / 2.0;
-            segment_colors.push(segment_avg);
-        }
-        segment_colors.push(255.0);
+        let segment_colors = Self::partial_segment_colors(&sorted_values);
 
         for chunk in pixels.chunks_exact_mut(4) {
             let r = chunk[0] as f32;
@@ -249,6 +402,103 @@ impl ImageProcessor {
         DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, pixels).unwrap())
     }
 
+    /// Partial模式下各区段的输出颜色（首段纯黑，末段纯白，中间等分）
+    fn partial_segment_colors(sorted_values: &[f32]) -> Vec<f32> {
+        let total_segments = sorted_values.len() + 2;
+        let segment_size = 255.0 / total_segments as f32;
+        let mut segment_colors = Vec::new();
+        segment_colors.push(0.0);
+        for i in 1..=sorted_values.len() {
+            let segment_start = i as f32 * segment_size;
+            let segment_end = (i + 1) as f32 * segment_size;
+            let segment_avg = (segment_start + segment_end) / 2.0;
+            segment_colors.push(segment_avg);
+        }
+        segment_colors.push(255.0);
+        segment_colors
+    }
+
+    /// 计算Average模式下实际输出的灰度级（按区段顺序，从暗到亮）
+    pub fn segment_levels(slider_values: &[f32]) -> Vec<u8> {
+        if slider_values.is_empty() {
+            return Vec::new();
+        }
+        let mut sorted_values = slider_values.to_vec();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Self::collect_levels(|g| Self::get_segment_value(g, &sorted_values))
+    }
+
+    /// 计算Partial模式下实际输出的灰度级（按区段顺序，从暗到亮）
+    pub fn segment_levels_partial(slider_values: &[f32]) -> Vec<u8> {
+        if slider_values.is_empty() {
+            return Vec::new();
+        }
+        let mut sorted_values = slider_values.to_vec();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let segment_colors = Self::partial_segment_colors(&sorted_values);
+        Self::collect_levels(|g| {
+            Self::get_segment_value_partial(g, &sorted_values, &segment_colors)
+        })
+    }
+
+    /// 单个像素在指定灰度模式下参与反射的灰度值（与整图转换的计算一致）
+    pub fn pixel_gray(pixel: [u8; 4], mode: GrayscaleMode) -> u8 {
+        let (r, g, b) = (pixel[0] as u32, pixel[1] as u32, pixel[2] as u32);
+        let luma = match mode {
+            GrayscaleMode::Default => ((299 * r + 587 * g + 114 * b) / 1000) as u8,
+            GrayscaleMode::Max => r.max(g).max(b) as u8,
+            GrayscaleMode::Min => r.min(g).min(b) as u8,
+        } as f32;
+        (0.299 * luma + 0.587 * luma + 0.114 * luma) as u8
+    }
+
+    /// 灰度值落入的区段序号（0为第一个滑块之前，与反射时的区段划分一致）
+    pub fn segment_index(gray_value: u8, slider_values: &[f32]) -> usize {
+        let mut sorted_values = slider_values.to_vec();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let gray_f32 = gray_value as f32;
+        if sorted_values.len() <= 1 {
+            return sorted_values.first().map_or(0, |&v| (gray_f32 >= v) as usize);
+        }
+        for i in 0..sorted_values.len() - 1 {
+            if gray_f32 >= sorted_values[i] && gray_f32 <= sorted_values[i + 1] {
+                return i + 1;
+            }
+        }
+        if gray_f32 <= sorted_values[0] {
+            0
+        } else {
+            sorted_values.len()
+        }
+    }
+
+    /// 单个灰度值在Average模式下的输出值
+    pub fn segment_output(gray_value: u8, slider_values: &[f32]) -> u8 {
+        let mut sorted_values = slider_values.to_vec();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Self::get_segment_value(gray_value, &sorted_values)
+    }
+
+    /// 单个灰度值在Partial模式下的输出值
+    pub fn segment_output_partial(gray_value: u8, slider_values: &[f32]) -> u8 {
+        let mut sorted_values = slider_values.to_vec();
+        sorted_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let segment_colors = Self::partial_segment_colors(&sorted_values);
+        Self::get_segment_value_partial(gray_value, &sorted_values, &segment_colors)
+    }
+
+    /// 遍历0-255所有灰度，按出现顺序收集不重复的输出值
+    fn collect_levels(map: impl Fn(u8) -> u8) -> Vec<u8> {
+        let mut levels: Vec<u8> = Vec::new();
+        for g in 0..=255u8 {
+            let v = map(g);
+            if !levels.contains(&v) {
+                levels.push(v);
+            }
+        }
+        levels
+    }
+
     /// 获取区段值 - Partial模式
     fn get_segment_value_partial(
         gray_value: u8,
@@ -280,10 +530,10 @@ impl ImageProcessor {
         // 处理边界情况
         if gray_f32 <= sorted_values[0] {
             // 小于第一个滑块的值，使用第一段颜色（纯黑色）
-            return segment_colors[0] as u8;
+            segment_colors[0] as u8
         } else {
             // 大于最后一个滑块的值，使用最后一段颜色（纯白色）
-            return segment_colors[segment_colors.len() - 1] as u8;
+            segment_colors[segment_colors.len() - 1] as u8
         }
     }
 
@@ -399,89 +649,619 @@ impl ImageProcessor {
             // 小于第一个滑块的值，使用第一个区段的平均值
             if sorted_values.len() > 1 {
                 let segment_avg = (0.0 + sorted_values[1]) / 2.0;
-                return segment_avg as u8;
+                segment_avg as u8
             } else {
-                return sorted_values[0] as u8;
+                sorted_values[0] as u8
             }
         } else {
             // 大于最后一个滑块的值，使用最后一个区段的平均值
             let last_idx = sorted_values.len() - 1;
             if last_idx > 0 {
                 let segment_avg = (sorted_values[last_idx - 1] + 255.0) / 2.0;
-                return segment_avg as u8;
+                segment_avg as u8
             } else {
-                return sorted_values[last_idx] as u8;
+                sorted_values[last_idx] as u8
             }
         }
     }
 
-    /// 将PNG写入包含文本元数据（tEXt）
+    /// 将PNG写入包含文本元数据；chunk_source 为PNG时沿用其中的附属块（pHYs、iCCP、gAMA、其他文本等），
+    /// 只替换同名的 key 条目（value 为None时只去掉旧条目）
+    ///
+    /// chunk_source 无法读取时仍写出图像，返回未能沿用元数据的原因，由调用方提示
     pub fn write_png_with_text_from_path(
         temp_path: &std::path::Path,
         out_path: &std::path::Path,
+        chunk_source: Option<&std::path::Path>,
         key: &str,
-        value: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        value: Option<&str>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
         let img = image::open(temp_path)?;
         let rgba = img.to_rgba8();
         let (width, height) = rgba.dimensions();
         let pixels = rgba.into_raw();
 
-        let file = std::fs::File::create(out_path)?;
-        let writer = std::io::BufWriter::new(file);
-        let mut encoder = png::Encoder::new(writer, width, height);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        // 写入文本元数据（键值）
-        encoder.add_text_chunk(key.to_string(), value.to_string())?;
-        let mut png_writer = encoder.write_header()?;
-        png_writer.write_image_data(&pixels)?;
-        Ok(())
+        let mut dropped = None;
+        let source_chunks = match chunk_source.filter(|p| Self::is_png(p)) {
+            Some(source) => match png_metadata::read_chunks_from_path(source) {
+                Ok(chunks) => chunks,
+                Err(e) => {
+                    dropped = Some(format!("Could not keep metadata of {}: {}", source.display(), e));
+                    Vec::new()
+                }
+            },
+            None => Vec::new(),
+        };
+        let (before, after) = png_metadata::carried_chunks(&source_chunks, Some(key));
+        let text = value.map(|value| png_metadata::Chunk::encode_text(key, value)).transpose()?;
+
+        Self::write_atomic(out_path, |w| {
+            let mut encoder = png::Encoder::new(w, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut png_writer = encoder.write_header()?;
+            for chunk in &before {
+                png_writer.write_chunk(png::chunk::ChunkType(chunk.kind), &chunk.data)?;
+            }
+            if let Some(text) = &text {
+                png_writer.write_chunk(png::chunk::ChunkType(text.kind), &text.data)?;
+            }
+            png_writer.write_image_data(&pixels)?;
+            for chunk in &after {
+                png_writer.write_chunk(png::chunk::ChunkType(chunk.kind), &chunk.data)?;
+            }
+            png_writer.finish()?;
+            Ok(())
+        })?;
+        Ok(dropped)
     }
 
-    /// 从PNG文件读取指定tEXt键的值（Latin-1文本），若无则返回None
+    /// 从PNG文件读取指定键的文本（tEXt、zTXt或iTXt），若无则返回None
+    ///
+    /// 会校验整个文件的CRC，损坏时报错；同一键出现多次时取最后一条
     pub fn read_png_text_value_from_path(
         path: &std::path::Path,
         key: &str,
     ) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        use std::io::Read;
-        let mut f = std::fs::File::open(path)?;
-        let mut buf = Vec::new();
-        f.read_to_end(&mut buf)?;
-
-        // PNG签名
-        let signature: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
-        if buf.len() < 8 || &buf[0..8] != signature {
-            return Ok(None);
-        }
-
-        let mut idx = 8;
-        while idx + 12 <= buf.len() {
-            let length = u32::from_be_bytes([buf[idx], buf[idx + 1], buf[idx + 2], buf[idx + 3]]) as usize;
-            let ctype = &buf[idx + 4..idx + 8];
-            let data_start = idx + 8;
-            let data_end = data_start + length;
-            if data_end + 4 > buf.len() { break; }
-
-            if ctype == b"tEXt" {
-                // 文本数据：keyword(1-79) + 0x00 + text
-                let data = &buf[data_start..data_end];
-                if let Some(pos) = data.iter().position(|&b| b == 0) {
-                    let keyword = &data[..pos];
-                    let text_bytes = &data[pos + 1..];
-                    if keyword == key.as_bytes() {
-                        // Latin-1转String
-                        let s: String = text_bytes.iter().map(|&b| b as char).collect();
-                        return Ok(Some(s));
+        match png_metadata::read_text_entries(path) {
+            Ok(entries) => Ok(entries.into_iter().rev().find(|e| e.keyword == key).map(|e| e.text)),
+            // 非PNG文件没有元数据
+            Err(png_metadata::ChunkError::NotPng) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 重采样到指定尺寸（用于按织机经纬密度生成精确的经线×纬线网格）
+    pub fn resample(
+        img: &DynamicImage,
+        new_width: u32,
+        new_height: u32,
+        method: ResampleMethod,
+    ) -> DynamicImage {
+        let src = img.to_rgba8();
+        let (sw, sh) = src.dimensions();
+        let new_width = new_width.max(1);
+        let new_height = new_height.max(1);
+        let scale_x = sw as f32 / new_width as f32;
+        let scale_y = sh as f32 / new_height as f32;
+        let mut out = image::RgbaImage::new(new_width, new_height);
+
+        for ty in 0..new_height {
+            for tx in 0..new_width {
+                let pixel = match method {
+                    ResampleMethod::Nearest => {
+                        let sx = (((tx as f32 + 0.5) * scale_x) as u32).min(sw - 1);
+                        let sy = (((ty as f32 + 0.5) * scale_y) as u32).min(sh - 1);
+                        *src.get_pixel(sx, sy)
                     }
+                    ResampleMethod::AreaAverage | ResampleMethod::MajorityVote => {
+                        // 目标像素覆盖的源区域（浮点坐标）
+                        let x0 = tx as f32 * scale_x;
+                        let x1 = (tx + 1) as f32 * scale_x;
+                        let y0 = ty as f32 * scale_y;
+                        let y1 = (ty + 1) as f32 * scale_y;
+
+                        let mut sum = [0f32; 4];
+                        let mut total = 0f32;
+                        let mut votes: Vec<([u8; 4], f32)> = Vec::new();
+
+                        for sy in (y0.floor() as u32)..(y1.ceil() as u32).min(sh) {
+                            let wy = (y1.min((sy + 1) as f32) - y0.max(sy as f32)).max(0.0);
+                            for sx in (x0.floor() as u32)..(x1.ceil() as u32).min(sw) {
+                                let wx = (x1.min((sx + 1) as f32) - x0.max(sx as f32)).max(0.0);
+                                let w = wx * wy;
+                                if w <= 0.0 {
+                                    continue;
+                                }
+                                let p = src.get_pixel(sx, sy).0;
+                                if method == ResampleMethod::AreaAverage {
+                                    for c in 0..4 {
+                                        sum[c] += p[c] as f32 * w;
+                                    }
+                                    total += w;
+                                } else if let Some(v) = votes.iter_mut().find(|v| v.0 == p) {
+                                    v.1 += w;
+                                } else {
+                                    votes.push((p, w));
+                                }
+                            }
+                        }
+
+                        if method == ResampleMethod::AreaAverage {
+                            if total > 0.0 {
+                                image::Rgba(sum.map(|c| (c / total).round().clamp(0.0, 255.0) as u8))
+                            } else {
+                                image::Rgba([0, 0, 0, 0])
+                            }
+                        } else {
+                            // 覆盖面积最大的颜色胜出，平局时取较亮的颜色（与Clean保持一致）
+                            votes
+                                .iter()
+                                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(a.0.cmp(&b.0)))
+                                .map(|v| image::Rgba(v.0))
+                                .unwrap_or(image::Rgba([0, 0, 0, 0]))
+                        }
+                    }
+                };
+                out.put_pixel(tx, ty, pixel);
+            }
+        }
+
+        DynamicImage::ImageRgba8(out)
+    }
+
+    /// 按排列方式构造可直接平铺的循环单元
+    pub fn build_repeat_unit(img: &DynamicImage, arrangement: RepeatArrangement) -> DynamicImage {
+        let src = img.to_rgba8();
+        let (w, h) = src.dimensions();
+        let unit = match arrangement {
+            RepeatArrangement::Straight => src,
+            // 右列整体下移半个高度
+            RepeatArrangement::HalfDrop => image::RgbaImage::from_fn(w * 2, h, |x, y| {
+                if x < w {
+                    *src.get_pixel(x, y)
+                } else {
+                    *src.get_pixel(x - w, (y + h - h / 2) % h)
+                }
+            }),
+            // 下行整体右移半个宽度
+            RepeatArrangement::HalfBrick => image::RgbaImage::from_fn(w, h * 2, |x, y| {
+                if y < h {
+                    *src.get_pixel(x, y)
+                } else {
+                    *src.get_pixel((x + w - w / 2) % w, y - h)
+                }
+            }),
+            // 2×2镜像：原图、水平翻转、垂直翻转、双向翻转
+            RepeatArrangement::Mirror => image::RgbaImage::from_fn(w * 2, h * 2, |x, y| {
+                let sx = if x < w { x } else { 2 * w - 1 - x };
+                let sy = if y < h { y } else { 2 * h - 1 - y };
+                *src.get_pixel(sx, sy)
+            }),
+        };
+        DynamicImage::ImageRgba8(unit)
+    }
+
+    /// 检查循环单元拼接处的不连续：返回右边缘不匹配的行号与下边缘不匹配的列号
+    pub fn seam_mismatches(unit: &DynamicImage, tolerance: u8) -> (Vec<u32>, Vec<u32>) {
+        let rgba = unit.to_rgba8();
+        let (w, h) = rgba.dimensions();
+        let differs = |a: &image::Rgba<u8>, b: &image::Rgba<u8>| {
+            a.0.iter().zip(b.0.iter()).any(|(&p, &q)| p.abs_diff(q) > tolerance)
+        };
+        let rows = (0..h)
+            .filter(|&y| differs(rgba.get_pixel(w - 1, y), rgba.get_pixel(0, y)))
+            .collect();
+        let cols = (0..w)
+            .filter(|&x| differs(rgba.get_pixel(x, h - 1), rgba.get_pixel(x, 0)))
+            .collect();
+        (rows, cols)
+    }
+
+    /// 以方形笔刷在指定像素处落笔（笔刷以该像素为中心）
+    pub fn stamp(img: &mut image::RgbaImage, x: i64, y: i64, size: u32, color: image::Rgba<u8>) {
+        let size = size.max(1) as i64;
+        let start = -(size - 1) / 2;
+        for dy in start..start + size {
+            for dx in start..start + size {
+                let (px, py) = (x + dx, y + dy);
+                if px >= 0 && py >= 0 && px < img.width() as i64 && py < img.height() as i64 {
+                    img.put_pixel(px as u32, py as u32, color);
                 }
             }
+        }
+    }
+
+    /// 用Bresenham算法绘制直线，每个点落一次笔刷
+    pub fn draw_line(
+        img: &mut image::RgbaImage,
+        from: (i64, i64),
+        to: (i64, i64),
+        size: u32,
+        color: image::Rgba<u8>,
+    ) {
+        let (mut x, mut y) = from;
+        let dx = (to.0 - x).abs();
+        let dy = -(to.1 - y).abs();
+        let sx = if x < to.0 { 1 } else { -1 };
+        let sy = if y < to.1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            Self::stamp(img, x, y, size, color);
+            if x == to.0 && y == to.1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// 绘制矩形（描边使用笔刷宽度，或整块填充）
+    pub fn draw_rect(
+        img: &mut image::RgbaImage,
+        a: (i64, i64),
+        b: (i64, i64),
+        size: u32,
+        filled: bool,
+        color: image::Rgba<u8>,
+    ) {
+        let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+        let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+        if filled {
+            for y in y0..=y1 {
+                Self::draw_line(img, (x0, y), (x1, y), 1, color);
+            }
+        } else {
+            Self::draw_line(img, (x0, y0), (x1, y0), size, color);
+            Self::draw_line(img, (x1, y0), (x1, y1), size, color);
+            Self::draw_line(img, (x1, y1), (x0, y1), size, color);
+            Self::draw_line(img, (x0, y1), (x0, y0), size, color);
+        }
+    }
+
+    /// 四连通泛洪填充
+    pub fn flood_fill(img: &mut image::RgbaImage, x: u32, y: u32, color: image::Rgba<u8>) {
+        let (w, h) = img.dimensions();
+        if x >= w || y >= h {
+            return;
+        }
+        let target = *img.get_pixel(x, y);
+        if target == color {
+            return;
+        }
+        let mut stack = vec![(x, y)];
+        while let Some((px, py)) = stack.pop() {
+            if *img.get_pixel(px, py) != target {
+                continue;
+            }
+            img.put_pixel(px, py, color);
+            if px > 0 { stack.push((px - 1, py)); }
+            if px + 1 < w { stack.push((px + 1, py)); }
+            if py > 0 { stack.push((px, py - 1)); }
+            if py + 1 < h { stack.push((px, py + 1)); }
+        }
+    }
 
-            // 跳到下一个chunk（包含CRC）
-            idx = data_end + 4;
-            if ctype == b"IEND" { break; }
+    /// 将图像中所有某一颜色替换为另一颜色
+    pub fn replace_color(img: &mut image::RgbaImage, from: image::Rgba<u8>, to: image::Rgba<u8>) {
+        for p in img.pixels_mut() {
+            if *p == from {
+                *p = to;
+            }
         }
-        Ok(None)
+    }
+
+    /// 若存在选区，则把处理结果限制在选区内（选区外保留临时文件中的当前结果）
+    pub fn limit_to_mask(
+        processed: DynamicImage,
+        mask: Option<&image::GrayImage>,
+        temp_path: &Option<PathBuf>,
+    ) -> DynamicImage {
+        let Some(mask) = mask.filter(|m| m.dimensions() == (processed.width(), processed.height())) else {
+            return processed;
+        };
+        match temp_path.as_ref().map(image::open) {
+            Some(Ok(base)) => Self::composite_masked(&base, &processed, mask),
+            _ => processed,
+        }
+    }
+
+    /// 将蒙版编码为行程文本："宽x高;游程1,游程2,..."（从未选中开始交替）
+    pub fn encode_mask_rle(mask: &image::GrayImage) -> String {
+        let mut runs: Vec<u32> = Vec::new();
+        let mut current = false;
+        let mut run = 0u32;
+        for p in mask.pixels() {
+            let selected = p[0] != 0;
+            if selected != current {
+                runs.push(run);
+                run = 0;
+                current = selected;
+            }
+            run += 1;
+        }
+        runs.push(run);
+        let runs = runs.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        format!("{}x{};{}", mask.width(), mask.height(), runs.join(","))
+    }
+
+    /// 解析行程文本为蒙版；格式不对、尺寸与图像不同或像素数不符时返回None
+    pub fn decode_mask_rle(text: &str, image_size: (u32, u32)) -> Option<image::GrayImage> {
+        let (size, runs) = text.split_once(';')?;
+        let (w, h) = size.split_once('x')?;
+        let (w, h) = (w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?);
+        // 尺寸来自文件元数据，先与图像比对，避免按任意大小分配内存
+        if (w, h) != image_size {
+            return None;
+        }
+        let total = (w as usize).checked_mul(h as usize)?;
+        let mut data: Vec<u8> = Vec::new();
+        let mut selected = false;
+        for run in runs.split(',') {
+            let run = run.trim().parse::<usize>().ok()?;
+            if data.len() + run > total {
+                return None;
+            }
+            data.resize(data.len() + run, if selected { 255 } else { 0 });
+            selected = !selected;
+        }
+        if data.len() != total {
+            return None;
+        }
+        image::GrayImage::from_raw(w, h, data)
+    }
+
+    /// 按选区合成：选区内取处理结果，选区外保留原有图像
+    pub fn composite_masked(
+        base: &DynamicImage,
+        processed: &DynamicImage,
+        mask: &image::GrayImage,
+    ) -> DynamicImage {
+        let mut result = base.to_rgba8();
+        let processed = processed.to_rgba8();
+        if result.dimensions() != processed.dimensions() || result.dimensions() != mask.dimensions() {
+            return DynamicImage::ImageRgba8(processed);
+        }
+        for ((out, p), m) in result.pixels_mut().zip(processed.pixels()).zip(mask.pixels()) {
+            if m[0] != 0 {
+                *out = *p;
+            }
+        }
+        DynamicImage::ImageRgba8(result)
+    }
+
+    /// 魔棒选择：与种子像素颜色差不超过容差的像素（可限定为连通区域）
+    pub fn magic_wand(
+        img: &DynamicImage,
+        x: u32,
+        y: u32,
+        tolerance: u8,
+        contiguous: bool,
+    ) -> image::GrayImage {
+        let rgba = img.to_rgba8();
+        let (w, h) = rgba.dimensions();
+        let mut mask = image::GrayImage::new(w, h);
+        if x >= w || y >= h {
+            return mask;
+        }
+        let seed = *rgba.get_pixel(x, y);
+        let matches = |p: &image::Rgba<u8>| {
+            p.0.iter().zip(seed.0.iter()).all(|(&a, &b)| a.abs_diff(b) <= tolerance)
+        };
+
+        if contiguous {
+            let mut stack = vec![(x, y)];
+            while let Some((px, py)) = stack.pop() {
+                if mask.get_pixel(px, py)[0] != 0 || !matches(rgba.get_pixel(px, py)) {
+                    continue;
+                }
+                mask.put_pixel(px, py, image::Luma([255]));
+                if px > 0 { stack.push((px - 1, py)); }
+                if px + 1 < w { stack.push((px + 1, py)); }
+                if py > 0 { stack.push((px, py - 1)); }
+                if py + 1 < h { stack.push((px, py + 1)); }
+            }
+        } else {
+            for (px, py, p) in rgba.enumerate_pixels() {
+                if matches(p) {
+                    mask.put_pixel(px, py, image::Luma([255]));
+                }
+            }
+        }
+        mask
+    }
+
+    /// 图像中不同颜色（级数）的数量，用于输出文件命名
+    pub fn count_levels(img: &DynamicImage) -> usize {
+        let rgba = img.to_rgba8();
+        rgba.pixels().map(|p| p.0).collect::<std::collections::HashSet<_>>().len()
+    }
+
+    /// 统计图像中出现的颜色，按亮度从暗到亮排序（即区段顺序）
+    pub fn image_levels(img: &DynamicImage) -> Result<Vec<[u8; 3]>, String> {
+        let rgba = img.to_rgba8();
+        let mut colors: Vec<[u8; 3]> = Vec::new();
+        let mut transparent = 0usize;
+        for p in rgba.pixels() {
+            if p[3] == 0 {
+                transparent += 1;
+                continue;
+            }
+            let c = [p[0], p[1], p[2]];
+            if !colors.contains(&c) {
+                colors.push(c);
+                if colors.len() > 256 {
+                    return Err("Image has more than 256 distinct colors".to_string());
+                }
+            }
+        }
+        if transparent > 0 {
+            return Err(format!(
+                "Image contains {} transparent pixels, indexed export needs an opaque image",
+                transparent
+            ));
+        }
+        colors.sort_by_key(|c| (299 * c[0] as u32 + 587 * c[1] as u32 + 114 * c[2] as u32, *c));
+        Ok(colors)
+    }
+
+    /// 将图像像素映射为调色板索引（每像素一个字节），颜色必须严格出现在调色板中
+    pub fn map_to_palette_indices(
+        img: &DynamicImage,
+        palette: &[[u8; 3]],
+    ) -> Result<Vec<u8>, String> {
+        let levels = Self::image_levels(img)?;
+        if levels.len() > palette.len() {
+            return Err(format!(
+                "Image contains {} distinct values but the palette only has {} entries",
+                levels.len(),
+                palette.len()
+            ));
+        }
+
+        let rgba = img.to_rgba8();
+        let mut indices = Vec::with_capacity((rgba.width() * rgba.height()) as usize);
+        for p in rgba.pixels() {
+            let c = [p[0], p[1], p[2]];
+            match palette.iter().position(|&e| e == c) {
+                Some(i) => indices.push(i as u8),
+                None => {
+                    return Err(format!(
+                        "Color ({}, {}, {}) is not in the palette",
+                        c[0], c[1], c[2]
+                    ))
+                }
+            }
+        }
+        Ok(indices)
+    }
+
+    /// 将索引按位深打包为行数据（1位时高位在前，每行字节对齐）
+    fn pack_index_rows(indices: &[u8], width: u32, height: u32, bit_depth: u8) -> Vec<Vec<u8>> {
+        let width = width as usize;
+        (0..height as usize)
+            .map(|y| {
+                let row = &indices[y * width..(y + 1) * width];
+                match bit_depth {
+                    1 => {
+                        let mut packed = vec![0u8; width.div_ceil(8)];
+                        for (x, &i) in row.iter().enumerate() {
+                            if i != 0 {
+                                packed[x / 8] |= 0x80 >> (x % 8);
+                            }
+                        }
+                        packed
+                    }
+                    _ => row.to_vec(),
+                }
+            })
+            .collect()
+    }
+
+    /// 写入索引PNG（PLTE按调色板顺序，索引N即第N级）
+    pub fn write_indexed_png(
+        out_path: &std::path::Path,
+        width: u32,
+        height: u32,
+        indices: &[u8],
+        palette: &[[u8; 3]],
+        bit_depth: u8,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let depth = match bit_depth {
+            1 => png::BitDepth::One,
+            8 => png::BitDepth::Eight,
+            _ => return Err(format!("Unsupported bit depth: {}", bit_depth).into()),
+        };
+        if palette.len() > (1usize << bit_depth) {
+            return Err(format!(
+                "{} palette entries do not fit into {} bit(s)",
+                palette.len(),
+                bit_depth
+            )
+            .into());
+        }
+
+        let data: Vec<u8> = Self::pack_index_rows(indices, width, height, bit_depth)
+            .into_iter()
+            .flatten()
+            .collect();
+        Self::write_atomic(out_path, |w| {
+            let mut encoder = png::Encoder::new(w, width, height);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(depth);
+            encoder.set_palette(palette.iter().flatten().copied().collect::<Vec<u8>>());
+            let mut png_writer = encoder.write_header()?;
+            png_writer.write_image_data(&data)?;
+            png_writer.finish()?;
+            Ok(())
+        })
+    }
+
+    /// 写入索引BMP（BITMAPINFOHEADER，8位或1位，行自下而上、4字节对齐）
+    pub fn write_indexed_bmp(
+        out_path: &std::path::Path,
+        width: u32,
+        height: u32,
+        indices: &[u8],
+        palette: &[[u8; 3]],
+        bit_depth: u8,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if bit_depth != 1 && bit_depth != 8 {
+            return Err(format!("Unsupported bit depth: {}", bit_depth).into());
+        }
+        let palette_entries = 1usize << bit_depth;
+        if palette.len() > palette_entries {
+            return Err(format!(
+                "{} palette entries do not fit into {} bit(s)",
+                palette.len(),
+                bit_depth
+            )
+            .into());
+        }
+
+        let rows = Self::pack_index_rows(indices, width, height, bit_depth);
+        let row_size = (width as usize * bit_depth as usize).div_ceil(32) * 4;
+        let pixel_offset = 14 + 40 + palette_entries * 4;
+        let image_size = row_size * height as usize;
+        let file_size = pixel_offset + image_size;
+
+        let mut buf: Vec<u8> = Vec::with_capacity(file_size);
+        // BITMAPFILEHEADER
+        buf.extend_from_slice(b"BM");
+        buf.extend_from_slice(&(file_size as u32).to_le_bytes());
+        buf.extend_from_slice(&[0u8; 4]);
+        buf.extend_from_slice(&(pixel_offset as u32).to_le_bytes());
+        // BITMAPINFOHEADER
+        buf.extend_from_slice(&40u32.to_le_bytes());
+        buf.extend_from_slice(&(width as i32).to_le_bytes());
+        buf.extend_from_slice(&(height as i32).to_le_bytes());
+        buf.extend_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&(bit_depth as u16).to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes()); // BI_RGB
+        buf.extend_from_slice(&(image_size as u32).to_le_bytes());
+        buf.extend_from_slice(&2835i32.to_le_bytes()); // 72 DPI
+        buf.extend_from_slice(&2835i32.to_le_bytes());
+        buf.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+        // 调色板（BGRA），不足部分补零
+        for i in 0..palette_entries {
+            let c = palette.get(i).copied().unwrap_or([0, 0, 0]);
+            buf.extend_from_slice(&[c[2], c[1], c[0], 0]);
+        }
+        // 像素数据自下而上
+        for row in rows.iter().rev() {
+            buf.extend_from_slice(row);
+            buf.resize(buf.len() + (row_size - row.len()), 0);
+        }
+
+        Self::write_bytes_atomic(out_path, &buf)
     }
 }
 
@@ -520,3 +1300,149 @@ impl UiUtils {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试输出文件放在系统临时目录，按进程号区分
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("weave_tool_egui-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn indexed_bmp_pads_rows_and_keeps_palette_order() {
+        let path = temp_file("indexed-1bit.bmp");
+        let palette = [[10, 20, 30], [200, 210, 220]];
+        // 3×2：第一行 0 1 1，第二行 1 0 0
+        ImageProcessor::write_indexed_bmp(&path, 3, 2, &[0, 1, 1, 1, 0, 0], &palette, 1).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        assert_eq!(&bytes[..2], b"BM");
+        assert_eq!(u32_at(2) as usize, bytes.len());
+        assert_eq!(u32_at(10), 14 + 40 + 2 * 4);
+        assert_eq!(u16::from_le_bytes([bytes[28], bytes[29]]), 1);
+        // 调色板为BGRA，顺序与传入一致
+        assert_eq!(&bytes[54..62], &[30, 20, 10, 0, 220, 210, 200, 0]);
+        // 每行补齐到4字节，自下而上
+        assert_eq!(&bytes[62..], &[0b1000_0000, 0, 0, 0, 0b0110_0000, 0, 0, 0]);
+    }
+
+    #[test]
+    fn indexed_bmp_8bit_fills_unused_palette_entries() {
+        let path = temp_file("indexed-8bit.bmp");
+        let palette = [[0, 0, 0], [255, 255, 255], [255, 0, 0]];
+        ImageProcessor::write_indexed_bmp(&path, 5, 1, &[2, 1, 0, 1, 2], &palette, 8).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let pixels = 14 + 40 + 256 * 4;
+        assert_eq!(bytes.len(), pixels + 8);
+        assert_eq!(&bytes[54 + 2 * 4..54 + 4 * 4], &[0, 0, 255, 0, 0, 0, 0, 0]);
+        assert_eq!(&bytes[pixels..], &[2, 1, 0, 1, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn indexed_png_round_trips_indices_and_palette() {
+        let path = temp_file("indexed-1bit.png");
+        let palette = [[10, 20, 30], [200, 210, 220]];
+        let indices = [0, 1, 1, 0, 1, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 1];
+        ImageProcessor::write_indexed_png(&path, 9, 2, &indices, &palette, 1).unwrap();
+        let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut data).unwrap();
+        let info = reader.info();
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        assert_eq!(info.bit_depth, png::BitDepth::One);
+        assert_eq!(info.palette.as_deref(), Some(&[10, 20, 30, 200, 210, 220][..]));
+        assert_eq!(frame.line_size, 2);
+        assert_eq!(&data[..frame.buffer_size()], &[0b0110_1000, 0b1000_0000, 0b1110_0000, 0b1000_0000]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn indexed_export_rejects_oversized_palettes() {
+        let path = temp_file("too-many-colors.bmp");
+        let palette = [[0, 0, 0], [1, 1, 1], [2, 2, 2]];
+        assert!(ImageProcessor::write_indexed_bmp(&path, 1, 1, &[0], &palette, 1).is_err());
+        assert!(ImageProcessor::write_indexed_png(&path, 1, 1, &[0], &palette, 1).is_err());
+        assert!(!path.exists());
+    }
+
+    fn gray_image(width: u32, height: u32, values: &[u8]) -> DynamicImage {
+        let rgba = image::RgbaImage::from_fn(width, height, |x, y| {
+            let v = values[(y * width + x) as usize];
+            image::Rgba([v, v, v, 255])
+        });
+        DynamicImage::ImageRgba8(rgba)
+    }
+
+    fn gray_values(img: &DynamicImage) -> Vec<u8> {
+        img.to_rgba8().pixels().map(|p| p[0]).collect()
+    }
+
+    #[test]
+    fn resample_nearest_picks_block_centres() {
+        let img = gray_image(4, 2, &[0, 10, 20, 30, 40, 50, 60, 70]);
+        let out = ImageProcessor::resample(&img, 2, 1, ResampleMethod::Nearest);
+        assert_eq!(gray_values(&out), vec![50, 70]);
+    }
+
+    #[test]
+    fn resample_area_average_weights_partial_pixels() {
+        // 3像素缩为2像素：每个目标像素覆盖1.5个源像素
+        let img = gray_image(3, 1, &[0, 90, 180]);
+        let out = ImageProcessor::resample(&img, 2, 1, ResampleMethod::AreaAverage);
+        assert_eq!(gray_values(&out), vec![30, 150]);
+    }
+
+    #[test]
+    fn resample_majority_vote_breaks_ties_towards_the_lighter_colour() {
+        let img = gray_image(4, 1, &[0, 200, 100, 100]);
+        let out = ImageProcessor::resample(&img, 2, 1, ResampleMethod::MajorityVote);
+        assert_eq!(gray_values(&out), vec![200, 100]);
+        // 多数胜出，不受出现顺序影响
+        let img = gray_image(3, 1, &[255, 0, 0]);
+        let out = ImageProcessor::resample(&img, 1, 1, ResampleMethod::MajorityVote);
+        assert_eq!(gray_values(&out), vec![0]);
+    }
+
+    #[test]
+    fn resample_majority_vote_keeps_levels_when_upscaling() {
+        let img = gray_image(2, 1, &[0, 255]);
+        let out = ImageProcessor::resample(&img, 5, 3, ResampleMethod::MajorityVote);
+        assert_eq!((out.width(), out.height()), (5, 3));
+        assert!(gray_values(&out).iter().all(|&v| v == 0 || v == 255));
+        assert_eq!(&gray_values(&out)[..5], &[0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn mask_rle_round_trips_at_odd_sizes() {
+        for (w, h) in [(1, 1), (3, 5), (7, 2), (13, 11)] {
+            let mask = image::GrayImage::from_fn(w, h, |x, y| image::Luma([if (x * 7 + y * 3) % 5 < 2 { 255 } else { 0 }]));
+            let text = ImageProcessor::encode_mask_rle(&mask);
+            assert_eq!(ImageProcessor::decode_mask_rle(&text, (w, h)), Some(mask));
+        }
+        // 以选中像素开头时首个游程为0
+        let mask = image::GrayImage::from_raw(3, 1, vec![255, 255, 0]).unwrap();
+        assert_eq!(ImageProcessor::encode_mask_rle(&mask), "3x1;0,2,1");
+    }
+
+    #[test]
+    fn mask_rle_rejects_bad_text_and_other_sizes() {
+        assert_eq!(ImageProcessor::decode_mask_rle("2x2;1,2,1", (2, 2)).map(|m| m.into_raw()), Some(vec![0, 255, 255, 0]));
+        // 像素数不符
+        assert!(ImageProcessor::decode_mask_rle("2x2;1,2", (2, 2)).is_none());
+        assert!(ImageProcessor::decode_mask_rle("2x2;1,2,5", (2, 2)).is_none());
+        // 格式错误
+        assert!(ImageProcessor::decode_mask_rle("2x2", (2, 2)).is_none());
+        assert!(ImageProcessor::decode_mask_rle("2*2;4", (2, 2)).is_none());
+        assert!(ImageProcessor::decode_mask_rle("2x2;a", (2, 2)).is_none());
+        // 与图像尺寸不同的蒙版在分配内存前即被拒绝
+        assert!(ImageProcessor::decode_mask_rle("2x2;4", (2, 3)).is_none());
+        assert!(ImageProcessor::decode_mask_rle("4000000000x4000000000;16000000000000000000", (2, 2)).is_none());
+    }
+}
@@ -0,0 +1,199 @@
+/// 主显示区的视图变换：缩放倍数与平移，以及屏幕坐标与图像坐标的换算
+///
+/// 图像中心位于显示区域中心加平移量处，缩放以图像像素为单位（1.0即100%）。
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ViewTransform {
+    pub zoom: f32,
+    pub pan: egui::Vec2,
+    // 最近一帧主显示区域的实际矩形（用于菜单中的适应窗口等操作）
+    pub viewport: egui::Rect,
+}
+
+impl Default for ViewTransform {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: egui::Vec2::ZERO,
+            viewport: egui::Rect::NOTHING,
+        }
+    }
+}
+
+impl ViewTransform {
+    pub const MIN_ZOOM: f32 = 0.01;
+    pub const MAX_ZOOM: f32 = 64.0;
+
+    /// 回到100%并居中
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.pan = egui::Vec2::ZERO;
+    }
+
+    /// 图像在屏幕上的矩形
+    pub fn image_rect(&self, image_size: egui::Vec2) -> egui::Rect {
+        egui::Rect::from_center_size(self.viewport.center() + self.pan, image_size * self.zoom)
+    }
+
+    /// 屏幕坐标 → 图像坐标（以像素为单位，可为小数或越界）
+    pub fn screen_to_image(&self, image_size: egui::Vec2, pos: egui::Pos2) -> egui::Pos2 {
+        let rect = self.image_rect(image_size);
+        ((pos - rect.min) / self.zoom).to_pos2()
+    }
+
+    /// 以屏幕上的某点为锚缩放：缩放前后该点下的图像位置保持不变
+    pub fn zoom_about(&mut self, new_zoom: f32, anchor: egui::Pos2) {
+        let new_zoom = new_zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let center = self.viewport.center() + self.pan;
+        let offset = (anchor - center) / self.zoom;
+        let new_center = anchor - offset * new_zoom;
+        self.pan = new_center - self.viewport.center();
+        self.zoom = new_zoom;
+    }
+
+    /// 以显示区域中心为锚缩放
+    pub fn zoom_centered(&mut self, new_zoom: f32) {
+        self.zoom_about(new_zoom, self.viewport.center());
+    }
+
+    /// 整幅图像放入显示区域
+    pub fn fit(&mut self, image_size: egui::Vec2) {
+        if let Some(zoom) = self.fit_ratios(image_size).map(|(x, y)| x.min(y)) {
+            self.zoom = zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+            self.pan = egui::Vec2::ZERO;
+        }
+    }
+
+    /// 图像铺满显示区域（短边贴合，长边可能超出）
+    pub fn fill(&mut self, image_size: egui::Vec2) {
+        if let Some(zoom) = self.fit_ratios(image_size).map(|(x, y)| x.max(y)) {
+            self.zoom = zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+            self.pan = egui::Vec2::ZERO;
+        }
+    }
+
+    fn fit_ratios(&self, image_size: egui::Vec2) -> Option<(f32, f32)> {
+        if !self.viewport.is_positive() || image_size.x <= 0.0 || image_size.y <= 0.0 {
+            return None;
+        }
+        Some((self.viewport.width() / image_size.x, self.viewport.height() / image_size.y))
+    }
+
+    /// 下一个整数档位：100%以上为整数倍，以下为1/n
+    pub fn step_zoom(zoom: f32, zoom_in: bool) -> f32 {
+        const EPS: f32 = 1e-4;
+        let next = if zoom_in {
+            if zoom >= 1.0 - EPS {
+                (zoom + EPS).floor() + 1.0
+            } else {
+                1.0 / ((1.0 / zoom - EPS).ceil() - 1.0).max(1.0)
+            }
+        } else if zoom > 1.0 + EPS {
+            (zoom - EPS).ceil() - 1.0
+        } else {
+            1.0 / ((1.0 / zoom + EPS).floor() + 1.0)
+        };
+        next.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM)
+    }
+
+    /// 处理滚轮、Ctrl+滚轮与触控板捏合缩放（以鼠标位置为锚）
+    pub fn handle_zoom_input(&mut self, ui: &egui::Ui) {
+        if !ui.ui_contains_pointer() {
+            return;
+        }
+        let (scroll, pinch, pointer) = ui.input(|i| (i.raw_scroll_delta.y, i.zoom_delta(), i.pointer.hover_pos()));
+        // 捏合或Ctrl+滚轮时egui已换算为缩放比例；普通滚轮每格约10%
+        let factor = if pinch != 1.0 { pinch } else { (scroll * 0.002).exp() };
+        if factor != 1.0 {
+            let anchor = pointer.unwrap_or(self.viewport.center());
+            self.zoom_about(self.zoom * factor, anchor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(zoom: f32, pan: egui::Vec2) -> ViewTransform {
+        ViewTransform {
+            zoom,
+            pan,
+            viewport: egui::Rect::from_min_size(egui::pos2(0.0, 30.0), egui::vec2(800.0, 600.0)),
+        }
+    }
+
+    fn approx(a: egui::Pos2, b: egui::Pos2) -> bool {
+        (a - b).length() < 1e-3
+    }
+
+    #[test]
+    fn image_is_centered_in_the_actual_viewport() {
+        let v = view(2.0, egui::Vec2::ZERO);
+        let rect = v.image_rect(egui::vec2(100.0, 50.0));
+        assert_eq!(rect.center(), egui::pos2(400.0, 330.0));
+        assert_eq!(rect.size(), egui::vec2(200.0, 100.0));
+    }
+
+    #[test]
+    fn screen_to_image_maps_rect_corners_and_center() {
+        let v = view(3.5, egui::vec2(-40.0, 25.0));
+        let size = egui::vec2(120.0, 80.0);
+        let rect = v.image_rect(size);
+        assert!(approx(v.screen_to_image(size, rect.min), egui::pos2(0.0, 0.0)));
+        assert!(approx(v.screen_to_image(size, rect.max), egui::pos2(120.0, 80.0)));
+        assert!(approx(v.screen_to_image(size, egui::pos2(360.0, 355.0)), egui::pos2(60.0, 40.0)));
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let size = egui::vec2(300.0, 200.0);
+        let mut v = view(1.0, egui::vec2(10.0, -20.0));
+        // 靠近边缘的锚点也不能漂移
+        for (zoom, anchor) in [(4.0, egui::pos2(5.0, 40.0)), (0.05, egui::pos2(790.0, 620.0)), (64.0, egui::pos2(123.0, 456.0))] {
+            let before = v.screen_to_image(size, anchor);
+            v.zoom_about(zoom, anchor);
+            assert!(approx(v.screen_to_image(size, anchor), before));
+            assert_eq!(v.zoom, zoom);
+        }
+    }
+
+    #[test]
+    fn zoom_is_clamped_to_range() {
+        let mut v = view(1.0, egui::Vec2::ZERO);
+        v.zoom_centered(1000.0);
+        assert_eq!(v.zoom, ViewTransform::MAX_ZOOM);
+        v.zoom_centered(0.0001);
+        assert_eq!(v.zoom, ViewTransform::MIN_ZOOM);
+    }
+
+    #[test]
+    fn fit_and_fill_use_the_viewport() {
+        let mut v = view(7.0, egui::vec2(50.0, 50.0));
+        v.fit(egui::vec2(1600.0, 300.0));
+        assert_eq!(v.zoom, 0.5);
+        assert_eq!(v.pan, egui::Vec2::ZERO);
+        v.fill(egui::vec2(1600.0, 300.0));
+        assert_eq!(v.zoom, 2.0);
+    }
+
+    #[test]
+    fn fit_ignores_empty_viewport() {
+        let mut v = ViewTransform::default();
+        v.fit(egui::vec2(100.0, 100.0));
+        assert_eq!(v.zoom, 1.0);
+    }
+
+    #[test]
+    fn integer_steps() {
+        assert_eq!(ViewTransform::step_zoom(1.0, true), 2.0);
+        assert_eq!(ViewTransform::step_zoom(2.4, true), 3.0);
+        assert_eq!(ViewTransform::step_zoom(2.4, false), 2.0);
+        assert_eq!(ViewTransform::step_zoom(3.0, false), 2.0);
+        assert_eq!(ViewTransform::step_zoom(1.0, false), 0.5);
+        assert_eq!(ViewTransform::step_zoom(0.5, false), 1.0 / 3.0);
+        assert_eq!(ViewTransform::step_zoom(0.5, true), 1.0);
+        assert_eq!(ViewTransform::step_zoom(0.3, true), 1.0 / 3.0);
+        assert_eq!(ViewTransform::step_zoom(64.0, true), ViewTransform::MAX_ZOOM);
+        assert_eq!(ViewTransform::step_zoom(0.01, false), ViewTransform::MIN_ZOOM);
+    }
+}